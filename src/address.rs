@@ -0,0 +1,143 @@
+// Native encoding of Particl mainnet addresses.
+use crate::script::ScriptTemplate;
+use bech32::{Bech32, Hrp};
+use serde::{Deserialize, Serialize};
+
+const PUBKEY_ADDRESS: u8 = 0x38;
+const SCRIPT_ADDRESS: u8 = 0x3c;
+const PUBKEY_ADDRESS_256: u8 = 0x39;
+const SCRIPT_ADDRESS_256: u8 = 0x3d;
+const STAKE_ONLY_HRP: &str = "pcs";
+
+// Addresses derived from a cold-staking script.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ColdstakeAddresses {
+    // Bech32 stake-only address, the way pools are identified.
+    pub stakeonly: String,
+    // Base58 address of the staking key.
+    pub stake: String,
+    // Base58 address of the spending key or script.
+    pub spend: String,
+}
+
+fn base58(version: u8, hash: &[u8]) -> String {
+    bs58::encode(hash).with_check_version(version).into_string()
+}
+
+pub fn stakeonly(keyhash: &[u8]) -> Option<String> {
+    let hrp = Hrp::parse(STAKE_ONLY_HRP).ok()?;
+    bech32::encode::<Bech32>(hrp, keyhash).ok()
+}
+
+// Base58 address of a pay to key hash or script hash template.
+pub fn encode(template: &ScriptTemplate) -> Option<String> {
+    let (hash, short, long) = match template {
+        ScriptTemplate::Pubkeyhash { keyhash } => (keyhash, PUBKEY_ADDRESS, PUBKEY_ADDRESS_256),
+        ScriptTemplate::Scripthash { scripthash } => {
+            (scripthash, SCRIPT_ADDRESS, SCRIPT_ADDRESS_256)
+        }
+        _ => return None,
+    };
+    let hash = hex::decode(hash).ok()?;
+    match hash.len() {
+        20 => Some(base58(short, &hash)),
+        32 => Some(base58(long, &hash)),
+        _ => None,
+    }
+}
+
+pub fn coldstake(template: &ScriptTemplate) -> Option<ColdstakeAddresses> {
+    match template {
+        ScriptTemplate::Coldstake {
+            stake_keyhash,
+            spend,
+        } => {
+            let keyhash = hex::decode(stake_keyhash).ok()?;
+            if keyhash.len() != 20 {
+                return None;
+            }
+            Some(ColdstakeAddresses {
+                stakeonly: stakeonly(&keyhash)?,
+                stake: base58(PUBKEY_ADDRESS, &keyhash),
+                spend: encode(spend)?,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::POOLS;
+
+    // Keyhashes behind the stake-only addresses of the known pools.
+    const POOL_KEYHASHES: [&str; 2] = [
+        "8f989228f922fb3cc981a5b9540d16880f84b68e",
+        "2d691b24a7b0abe25960678d1d4a25375fedad80",
+    ];
+    // Script hash of the first treasury address.
+    const TREASURY_SCRIPTHASH: &str = "616fec56cfcc7a6b7a5fcc844b9d73c532321b59";
+
+    #[test]
+    fn stakeonly_derives_pool_addresses() {
+        for (pool, keyhash) in POOLS.iter().zip(POOL_KEYHASHES) {
+            let keyhash = hex::decode(keyhash).unwrap();
+            assert_eq!(stakeonly(&keyhash).as_deref(), Some(pool.pubkey));
+        }
+    }
+
+    #[test]
+    fn encode_base58_addresses() {
+        let cases = [
+            (
+                ScriptTemplate::Scripthash {
+                    scripthash: TREASURY_SCRIPTHASH.to_string(),
+                },
+                Some("RJAPhgckEgRGVPZa9WoGSWW24spskSfLTQ"),
+            ),
+            (
+                ScriptTemplate::Pubkeyhash {
+                    keyhash: POOL_KEYHASHES[0].to_string(),
+                },
+                Some("Pm23YKyqsX1uHu6HyLpRULnpBnxSwsQftu"),
+            ),
+            (
+                ScriptTemplate::Pubkeyhash {
+                    keyhash: "22".repeat(32),
+                },
+                Some("2v4TwrBE2B7CuQQJ8PL2EBvSneidQdgMxWypQVW1ze3po4Q6LUH"),
+            ),
+            (
+                ScriptTemplate::Scripthash {
+                    scripthash: "22".repeat(32),
+                },
+                Some("33qSemyMRsp7Sgj7khp23UKYT7BW1T3s75GFP6FbyG5NmazKd5k"),
+            ),
+            (
+                ScriptTemplate::Pubkeyhash {
+                    keyhash: "22".repeat(16),
+                },
+                None,
+            ),
+            (ScriptTemplate::Nonstandard, None),
+        ];
+        for (template, expected) in cases {
+            assert_eq!(encode(&template).as_deref(), expected, "{:?}", template);
+        }
+    }
+
+    #[test]
+    fn coldstake_derives_all_addresses() {
+        let template = ScriptTemplate::Coldstake {
+            stake_keyhash: POOL_KEYHASHES[0].to_string(),
+            spend: Box::new(ScriptTemplate::Scripthash {
+                scripthash: TREASURY_SCRIPTHASH.to_string(),
+            }),
+        };
+        let addresses = coldstake(&template).unwrap();
+        assert_eq!(addresses.stakeonly, POOLS[0].pubkey);
+        assert_eq!(addresses.stake, "Pm23YKyqsX1uHu6HyLpRULnpBnxSwsQftu");
+        assert_eq!(addresses.spend, "RJAPhgckEgRGVPZa9WoGSWW24spskSfLTQ");
+    }
+}
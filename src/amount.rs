@@ -0,0 +1,69 @@
+// Monetary amounts kept as integer satoshis.
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt;
+
+pub const COIN: u64 = 100_000_000;
+
+// Persisted as an integer number of satoshis.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Amount(pub u64);
+
+impl Amount {
+    // particld reports amounts as decimal coins, which are exact to 8 places only after rounding.
+    pub fn from_coins(coins: f64) -> Self {
+        Amount((coins * COIN as f64).round() as u64)
+    }
+    pub fn sat(self) -> u64 {
+        self.0
+    }
+}
+
+// Fixed 8 decimal places, the way particld displays amounts.
+impl fmt::Display for Amount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{:08}", self.0 / COIN, self.0 % COIN)
+    }
+}
+
+// particld reports amounts as decimal coins. They are replaced by satoshis before
+// deserializing, so an Amount reads back exactly what it serializes to.
+// Integers are satoshis already, which makes converting stored blocks twice harmless.
+pub fn coins_to_sats(object: &mut Value, keys: &[&str]) {
+    for key in keys {
+        if let Some(coins) = object
+            .get(key)
+            .filter(|coins| coins.is_f64())
+            .and_then(Value::as_f64)
+        {
+            object[*key] = Value::from(Amount::from_coins(coins).sat());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn amounts_round_trip_as_satoshis() {
+        for amount in [Amount(0), Amount(1), Amount(COIN), Amount(u64::MAX)] {
+            let value = serde_json::to_value(amount).unwrap();
+            assert_eq!(value, json!(amount.sat()));
+            assert_eq!(serde_json::from_value::<Amount>(value).unwrap(), amount);
+        }
+    }
+
+    #[test]
+    fn coins_are_converted_to_satoshis() {
+        let mut object = json!({"value": 1.00000001, "fee": 0.1, "valueSat": 100000001, "n": 0});
+        coins_to_sats(&mut object, &["value", "fee", "valueSat", "missing"]);
+        let converted = json!({"value": 100000001, "fee": 10000000, "valueSat": 100000001, "n": 0});
+        assert_eq!(object, converted);
+        coins_to_sats(&mut object, &["value", "fee", "valueSat", "missing"]);
+        assert_eq!(object, converted);
+        assert_eq!(Amount(100000001).to_string(), "1.00000001");
+    }
+}
@@ -1,7 +1,11 @@
-use clap::{command, Arg, ArgMatches};
+use crate::{
+    registry::{self, ProposalMeta},
+    storage::StorageProfile,
+};
+use clap::{command, value_parser, Arg, ArgAction, ArgMatches, Command};
 
 pub fn args() -> ArgMatches {
-    let args = command!()
+    command!()
         .arg(
             Arg::new("Particld IP")
                 .long("rpc-ip")
@@ -32,7 +36,119 @@ pub fn args() -> ArgMatches {
                 .help("IP address of the SurrealDB instance")
                 .required(true),
         )
-        .get_matches();
+        .arg(
+            Arg::new("leaderboard window")
+                .long("leaderboard-window")
+                .help("Number of most recent blocks over which staker shares are computed")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("720"),
+        )
+        .arg(
+            Arg::new("decentralization windows")
+                .long("decentralization-windows")
+                .help("Comma separated sizes of block windows for decentralization metrics")
+                .value_parser(value_parser!(u64).range(1..))
+                .value_delimiter(',')
+                .default_value("720,5040,21600"),
+        )
+        .arg(
+            Arg::new("retally interval")
+                .long("retally-interval")
+                .help("Number of blocks between re-tallies of the proposals voted on in the meantime")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("10"),
+        )
+        .arg(
+            Arg::new("tally check every")
+                .long("tally-check-every")
+                .help("Cross-check every Nth re-tally against tallyvotes of particld, 0 disables the check")
+                .value_parser(value_parser!(u64))
+                .default_value("100"),
+        )
+        .arg(
+            Arg::new("turnout window")
+                .long("turnout-window")
+                .help("Number of most recent blocks over which governance turnout is computed")
+                .value_parser(value_parser!(u64).range(1..))
+                .default_value("720"),
+        )
+        .arg(
+            Arg::new("proposal registry")
+                .long("proposal-registry")
+                .help("JSON or TOML (*.toml) file with metadata and voting periods of proposals, for example 'configs/proposals.json'"),
+        )
+        .arg(
+            Arg::new("storage profile")
+                .long("storage-profile")
+                .help("Which block fields are stored: everything, everything but raw data, or staking and governance only")
+                .value_parser(value_parser!(StorageProfile))
+                .default_value("full"),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Applies pending database migrations and exits")
+                .arg(
+                    Arg::new("dry run")
+                        .long("dry-run")
+                        .help("Only list the migrations which would be applied")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("tally")
+                .about("Prints the tally of a proposal computed from the recorded votes and exits")
+                .arg(
+                    Arg::new("proposal")
+                        .long("proposal")
+                        .help("Proposal ID")
+                        .value_parser(value_parser!(u64))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .help("First block of the tally")
+                        .value_parser(value_parser!(u64))
+                        .default_value("616958"),
+                )
+                .arg(
+                    Arg::new("height")
+                        .long("height")
+                        .help("Last block of the tally, the last recorded one by default")
+                        .value_parser(value_parser!(u64)),
+                ),
+        )
+        .get_matches()
+}
+
+// Indexing options needed down to every scanned block.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub leaderboard_window: u64,
+    pub decentralization_windows: Vec<u64>,
+    pub retally_interval: u64,
+    pub tally_check_every: u64,
+    pub turnout_window: u64,
+    pub registry: Vec<ProposalMeta>,
+    pub storage_profile: StorageProfile,
+}
 
-    return args;
+impl Settings {
+    pub fn from_args(args: &ArgMatches) -> Self {
+        Settings {
+            leaderboard_window: *args.get_one::<u64>("leaderboard window").unwrap(),
+            decentralization_windows: args
+                .get_many::<u64>("decentralization windows")
+                .unwrap()
+                .copied()
+                .collect(),
+            retally_interval: *args.get_one::<u64>("retally interval").unwrap(),
+            tally_check_every: *args.get_one::<u64>("tally check every").unwrap(),
+            turnout_window: *args.get_one::<u64>("turnout window").unwrap(),
+            registry: args
+                .get_one::<String>("proposal registry")
+                .map_or(Vec::new(), |path| registry::load(path)),
+            storage_profile: *args.get_one::<StorageProfile>("storage profile").unwrap(),
+        }
+    }
 }
@@ -0,0 +1,125 @@
+// Running public balances of addresses.
+use crate::utxo::Utxo;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Balance {
+    pub address: String,
+    // Height of the last change.
+    pub height: u64,
+    pub balance: u64,
+    pub delta: i64,
+}
+
+// Only outputs paying to a single address are attributed to it.
+// Multisig and nonstandard outputs have no single owner.
+fn owner(utxo: &Utxo) -> Option<&String> {
+    match (&utxo.addresses, utxo.valuesat) {
+        (Some(addresses), Some(_)) if addresses.len() == 1 => addresses.first(),
+        _ => None,
+    }
+}
+
+// Net change of every address touched by the block.
+pub fn deltas(created: &[Utxo], spent: &[Utxo]) -> HashMap<String, i64> {
+    let mut deltas: HashMap<String, i64> = HashMap::new();
+    for utxo in created.iter() {
+        if let Some(address) = owner(utxo) {
+            *deltas.entry(address.clone()).or_default() += utxo.valuesat.unwrap() as i64;
+        }
+    }
+    for utxo in spent.iter() {
+        if let Some(address) = owner(utxo) {
+            *deltas.entry(address.clone()).or_default() -= utxo.valuesat.unwrap() as i64;
+        }
+    }
+    deltas.retain(|_, delta| *delta != 0);
+    deltas
+}
+
+// Applies deltas on top of the last known balances.
+pub fn apply(current: &[Balance], deltas: &HashMap<String, i64>, height: u64) -> Vec<Balance> {
+    deltas
+        .iter()
+        .map(|(address, delta)| {
+            let previous = current
+                .iter()
+                .find(|balance| &balance.address == address)
+                .map_or(0, |balance| balance.balance);
+            let balance = previous as i64 + delta;
+            if balance < 0 {
+                error!(
+                    "Balance of {} went negative at height {}. Database is insane!",
+                    address, height
+                );
+                std::process::exit(1);
+            }
+            Balance {
+                address: address.clone(),
+                height,
+                balance: balance as u64,
+                delta: *delta,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utxo(address: &str, valuesat: u64) -> Utxo {
+        Utxo {
+            outpoint: format!("{}:0", address),
+            txid: String::new(),
+            n: 0,
+            height: 0,
+            output_type: "standard".to_string(),
+            valuesat: Some(valuesat),
+            value_commitment: None,
+            addresses: Some(vec![address.to_string()]),
+            stakeaddresses: None,
+            template: None,
+            coldstake: None,
+            spent: None,
+        }
+    }
+
+    fn balance(address: &str, balance: u64) -> Balance {
+        Balance {
+            address: address.to_string(),
+            height: 1,
+            balance,
+            delta: balance as i64,
+        }
+    }
+
+    #[test]
+    fn deltas_net_out_and_skip_unowned_outputs() {
+        let mut multisig = utxo("a", 7);
+        multisig.addresses = Some(vec!["a".to_string(), "b".to_string()]);
+        let mut blind = utxo("a", 0);
+        blind.valuesat = None;
+        let created = [utxo("a", 10), utxo("b", 5), multisig, blind];
+        let spent = [utxo("a", 4), utxo("b", 5)];
+        let deltas = deltas(&created, &spent);
+        assert_eq!(deltas, HashMap::from([("a".to_string(), 6)]));
+    }
+
+    #[test]
+    fn apply_adds_deltas_to_previous_balances() {
+        let current = [balance("a", 100), balance("b", 20)];
+        // (address, delta, expected balance)
+        let cases = [("a", -40, 60), ("b", 5, 25), ("c", 7, 7), ("b", -20, 0)];
+        for (address, delta, expected) in cases {
+            let deltas = HashMap::from([(address.to_string(), delta)]);
+            let applied = apply(&current, &deltas, 2);
+            assert_eq!(applied.len(), 1);
+            assert_eq!(applied[0].address, address);
+            assert_eq!(applied[0].height, 2);
+            assert_eq!(applied[0].balance, expected, "{} {:+}", address, delta);
+            assert_eq!(applied[0].delta, delta);
+        }
+    }
+}
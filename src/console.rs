@@ -1,10 +1,16 @@
 use crate::{
+    address,
+    amount::{self, Amount},
     console::Vout::Data,
     db,
-    pools::{Pool, POOLS},
+    pools::{self, Pool},
+    rewards::CoinstakeReward,
     rpc::{call, RPCURL},
+    script::{classify, ScriptTemplate},
+    tally::TALLY_START,
+    turnout::Participation,
 };
-use serde::{Deserialize, Serialize};
+use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use std::{collections::HashMap, error::Error};
 use surrealdb::{engine::remote::ws::Client, Surreal};
@@ -28,7 +34,7 @@ pub struct BlockData {
     pub size: u64,
     pub stakekernelblockhash: Option<String>,
     pub stakekernelscript: Option<String>,
-    pub stakekernelvalue: Option<f64>,
+    pub stakekernelvalue: Option<Amount>,
     pub strippedsize: u64,
     pub time: u64,
     pub tx: Vec<Transaction>,
@@ -39,6 +45,7 @@ pub struct BlockData {
     pub witnessmerkleroot: String,
     pub coldstaking: Option<Pool>,
     pub voting_info: Option<Vote>,
+    pub coinstake: Option<CoinstakeReward>,
 }
 
 impl BlockData {
@@ -47,65 +54,72 @@ impl BlockData {
         db: &Surreal<Client>,
         rpcurl: &RPCURL,
     ) -> Result<(), Box<dyn Error>> {
-        let hasstakeaddress: Option<Vec<String>> = match self.tx[0].vout[1].clone() {
-            Vout::Standard {
-                n: _,
-                vout_type: _,
-                value: _,
-                valuesat: _,
-                scriptpubkey,
-            } => scriptpubkey.stakeaddresses,
+        // Coinstake pays the stake back in the second output, right after the data output.
+        let scriptpubkey = match self.tx.first().and_then(|tx| tx.vout.get(1)) {
+            Some(Vout::Standard { scriptpubkey, .. }) => scriptpubkey.clone(),
             _ => {
-                error!("Unexpected type of vout when validating address.");
-                std::process::exit(1);
+                self.coldstaking = None;
+                return Ok(());
             }
         };
-        match hasstakeaddress {
-            Some(unchecked_raw_stakeaddresses) => {
+        let unchecked_raw_stakeaddress = match scriptpubkey.stakeaddresses {
+            Some(stakeaddresses) if !stakeaddresses.is_empty() => stakeaddresses[0].clone(),
+            _ => {
+                self.coldstaking = None;
+                return Ok(());
+            }
+        };
+        let native = scriptpubkey.template.as_ref().and_then(address::coldstake);
+        match native {
+            Some(addresses)
+                if addresses.stake == unchecked_raw_stakeaddress
+                    || addresses.stakeonly == unchecked_raw_stakeaddress =>
+            {
+                trace!("Stakeaddress decoded from the script.");
+                let coldstaking = pools::identify(&addresses.stakeonly);
+                // Kept up to date as when the address is validated through RPC.
+                let stakeaddr_for_db = Stakeaddress {
+                    raw: unchecked_raw_stakeaddress,
+                    pool: coldstaking.clone(),
+                };
+                db::regstakeaddress(db, &stakeaddr_for_db).await?;
+                self.coldstaking = Some(coldstaking);
+            }
+            _ => {
+                trace!("Unrecognized cold-staking script, falling back to RPC.");
                 let coldstaking =
-                    check_stakeaddress_in_db(&unchecked_raw_stakeaddresses[0], db, rpcurl).await?;
+                    check_stakeaddress_in_db(&unchecked_raw_stakeaddress, db, rpcurl).await?;
                 self.coldstaking = Some(coldstaking);
-                Ok(())
             }
-            None => {
-                self.coldstaking = None;
-                Ok(())
+        }
+        Ok(())
+    }
+    fn classify_scripts(&mut self) {
+        for tx in self.tx.iter_mut() {
+            for vout in tx.vout.iter_mut() {
+                if let Vout::Standard { scriptpubkey, .. } = vout {
+                    scriptpubkey.template = Some(classify(&scriptpubkey.hex));
+                }
             }
         }
     }
     fn read_vote(&mut self) {
-        let vout = self.tx[0].vout[0].clone();
-        match vout {
-            Data {
-                n: _,
-                data_hex: _,
-                smsgdifficulty: _,
-                smsgfeerate: _,
-                treasury_fund_cfwd: _,
-                vout_type: _,
-                vote,
-            } => match vote {
-                Some(content) => {
-                    let parsed: Vec<u64> = content
-                        .split(", ")
-                        .map(|x| x.parse::<u64>().unwrap())
-                        .collect();
-                    if parsed.len() != 2 {
-                        error!("Sanity checks for parsed vote stats failed.");
-                        std::process::exit(1);
-                    }
-                    self.voting_info = Some(Vote {
-                        proposal_id: parsed[0],
-                        voted_for_option: parsed[1],
-                    });
-                }
-                None => {
-                    self.voting_info = None;
+        let vout = self.tx.first().and_then(|tx| tx.vout.first()).cloned();
+        self.voting_info = match vout {
+            Some(Data {
+                vote: Some(content),
+                ..
+            }) => {
+                let vote = parse_vote(&content);
+                if vote.is_none() {
+                    warn!(
+                        "Block {} carries a malformed vote '{}'.",
+                        self.height, content
+                    );
                 }
-            },
-            _ => {
-                self.voting_info = None;
+                vote
             }
+            _ => None,
         }
     }
 }
@@ -139,25 +153,11 @@ async fn validateaddress(
     let arg = format!("validateaddress {} true", stakeaddress);
     let value = call(&arg, rpcurl)?;
     let poolkey: String = serde_json::from_value(value["stakeonly_address"].clone()).unwrap();
-    // Default is no pool.
-    let mut coldstaking = Pool {
-        pubkey: poolkey.clone(),
-        url: None,
-        pool_is_active: None,
-    };
-    for known_pool in POOLS {
-        if &poolkey == known_pool.pubkey {
-            trace!("Stakeaddress belongs to a known pool.");
-            coldstaking = known_pool.getpool();
-            let stakeaddr_for_db = Stakeaddress {
-                raw: stakeaddress.to_string(),
-                pool: coldstaking.clone(),
-            };
-            db::regstakeaddress(db, &stakeaddr_for_db).await?;
-            return Ok(coldstaking);
-        }
+    let coldstaking = pools::identify(&poolkey);
+    match coldstaking.url {
+        Some(_) => trace!("Stakeaddress belongs to a known pool."),
+        None => trace!("Stakeaddress is of an unknown origin."),
     }
-    trace!("Stakeaddress is of an unknown origin.");
     let stakeaddr_for_db = Stakeaddress {
         raw: stakeaddress.to_string(),
         pool: coldstaking.clone(),
@@ -193,17 +193,25 @@ pub enum Vout {
         n: u64,
         data_hex: String,
         smsgdifficulty: Option<String>,
-        smsgfeerate: Option<f64>,
-        treasury_fund_cfwd: Option<f64>,
-        #[serde(rename(deserialize = "type", serialize = "type"))]
+        smsgfeerate: Option<Amount>,
+        treasury_fund_cfwd: Option<Amount>,
+        // Fee stated explicitly by transactions with blind or anon outputs.
+        ct_fee: Option<Amount>,
+        #[serde(
+            rename(deserialize = "type", serialize = "type"),
+            deserialize_with = "data_type"
+        )]
         vout_type: String,
         vote: Option<String>,
     },
     Standard {
         n: u64,
-        #[serde(rename(deserialize = "type", serialize = "type"))]
+        #[serde(
+            rename(deserialize = "type", serialize = "type"),
+            deserialize_with = "standard_type"
+        )]
         vout_type: String,
-        value: f64,
+        value: Amount,
         #[serde(rename(deserialize = "valueSat", serialize = "valueSat"))]
         valuesat: u64,
         #[serde(rename(deserialize = "scriptPubKey", serialize = "scriptPubKey"))]
@@ -211,7 +219,10 @@ pub enum Vout {
     },
     Blind {
         n: u64,
-        #[serde(rename(deserialize = "type", serialize = "type"))]
+        #[serde(
+            rename(deserialize = "type", serialize = "type"),
+            deserialize_with = "blind_type"
+        )]
         vout_type: String,
         pubkey: Option<String>,
         #[serde(rename(deserialize = "valueCommitment", serialize = "valueCommitment"))]
@@ -221,7 +232,10 @@ pub enum Vout {
     },
     Anon {
         n: u64,
-        #[serde(rename(deserialize = "type", serialize = "type"))]
+        #[serde(
+            rename(deserialize = "type", serialize = "type"),
+            deserialize_with = "anon_type"
+        )]
         vout_type: String,
         pubkey: Option<String>,
         #[serde(rename(deserialize = "valueCommitment", serialize = "valueCommitment"))]
@@ -229,6 +243,36 @@ pub enum Vout {
         data_hex: String,
         rangeproof: String,
     },
+    // Any shape not covered above, kept as reported by particld.
+    Unknown(Value),
+}
+
+// Data, blind and anon outputs share their fields, so each untagged variant only accepts its own type.
+fn output_type<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    expected: &str,
+) -> Result<String, D::Error> {
+    let output_type = String::deserialize(deserializer)?;
+    if output_type != expected {
+        return Err(de::Error::custom(format!("not a {} output", expected)));
+    }
+    Ok(output_type)
+}
+
+fn data_type<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    output_type(deserializer, "data")
+}
+
+fn standard_type<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    output_type(deserializer, "standard")
+}
+
+fn blind_type<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    output_type(deserializer, "blind")
+}
+
+fn anon_type<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    output_type(deserializer, "anon")
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -241,11 +285,16 @@ pub struct ScriptPubKey {
     pub req_sigs: Option<u64>,
     #[serde(rename(deserialize = "type", serialize = "type"))]
     pub staking_type: String,
+    pub template: Option<ScriptTemplate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Vin {
+    Coinbase {
+        coinbase: String,
+        sequence: u64,
+    },
     Standard {
         txid: String,
         vout: u64,
@@ -260,6 +309,76 @@ pub enum Vin {
         txinwitness: Vec<String>,
         sequence: u64,
     },
+    // Any shape not covered above, kept as reported by particld.
+    Unknown(Value),
+}
+
+// Votes are reported as '<proposal_id>, <option>'.
+fn parse_vote(content: &str) -> Option<Vote> {
+    let parsed: Vec<u64> = content
+        .split(", ")
+        .map(|x| x.parse::<u64>().ok())
+        .collect::<Option<Vec<u64>>>()?;
+    match parsed[..] {
+        [proposal_id, voted_for_option] => Some(Vote {
+            proposal_id,
+            voted_for_option,
+        }),
+        _ => None,
+    }
+}
+
+// Input or output which didn't match any known shape.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnknownShape {
+    pub height: u64,
+    pub txid: String,
+    // Either 'vin', 'vout' or 'vote' for a coinstake data output with a malformed vote.
+    pub kind: String,
+    pub index: u64,
+    pub raw: Value,
+}
+
+impl BlockData {
+    pub fn unknown_shapes(&self) -> Vec<UnknownShape> {
+        let mut shapes = Vec::new();
+        for (position, tx) in self.tx.iter().enumerate() {
+            let vins = tx.vin.iter().enumerate().filter_map(|(i, vin)| match vin {
+                Vin::Unknown(raw) => Some(("vin", i, raw.clone())),
+                _ => None,
+            });
+            let vouts = tx
+                .vout
+                .iter()
+                .enumerate()
+                .filter_map(|(i, vout)| match vout {
+                    Vout::Unknown(raw) => Some(("vout", i, raw.clone())),
+                    _ => None,
+                });
+            // Only the first output of the coinstake carries a vote.
+            let vote = match tx.vout.first() {
+                Some(
+                    vout @ Data {
+                        vote: Some(content),
+                        ..
+                    },
+                ) if position == 0 && parse_vote(content).is_none() => {
+                    Some(("vote", 0, serde_json::to_value(vout).unwrap_or_default()))
+                }
+                _ => None,
+            };
+            for (kind, index, raw) in vins.chain(vouts).chain(vote) {
+                shapes.push(UnknownShape {
+                    height: self.height,
+                    txid: tx.txid.clone(),
+                    kind: kind.to_string(),
+                    index: index as u64,
+                    raw,
+                });
+            }
+        }
+        shapes
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -280,64 +399,114 @@ pub struct Vote {
     pub voted_for_option: u64,
 }
 
-impl Vote {
-    pub async fn gen_proposal(&self, rpcurl: &RPCURL) -> Result<Proposal, Box<dyn Error>> {
-        Ok(Proposal {
-            proposal_id: self.proposal_id,
-            stats: self.count_stats(rpcurl).await?,
-        })
-    }
-    async fn count_stats(
-        &self,
-        rpcurl: &RPCURL,
-    ) -> Result<HashMap<String, (u64, f64)>, Box<dyn Error>> {
-        Ok(tallyvotes(*&self.proposal_id, rpcurl).await?)
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Proposal {
     pub proposal_id: u64,
-    pub stats: HashMap<String, (u64, f64)>,
+    pub height_start: u64,
+    // Last block counted by the tally.
+    pub height: u64,
+    pub blocks_counted: u64,
+    pub blocks_voted: u64,
+    // Satoshis staked by the voting blocks. Unknown to tallyvotes.
+    pub weight: Option<u64>,
+    pub options: Vec<OptionStats>,
+    #[serde(default)]
+    pub participation: Option<Participation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionStats {
+    pub option: u64,
+    pub label: Option<String>,
+    pub blocks: u64,
+    // Basis points of the blocks counted.
+    pub share: u64,
+    pub weight: Option<u64>,
+    // Basis points of the weight of all voting blocks.
+    pub weight_share: Option<u64>,
 }
 
-async fn tallyvotes(
+pub async fn tallyvotes(
     proposal_id: u64,
+    height: u64,
     rpcurl: &RPCURL,
-) -> Result<HashMap<String, (u64, f64)>, Box<dyn Error>> {
+) -> Result<Proposal, Box<dyn Error>> {
     // 616959 is the block at which the first vote was recorded. Hence the minimum for the range in tallyvotes.
-    let arg = format!("tallyvotes {} 616958 {}", proposal_id, i32::MAX);
+    let arg = format!("tallyvotes {} {} {}", proposal_id, TALLY_START, height);
     let context = call(&arg, rpcurl)?;
     let rawmap: HashMap<String, Value> = serde_json::from_value(context)?;
-    let mut hmap: HashMap<String, (u64, f64)> = rawmap
-        .iter()
-        .map(|val| {
-            (
-                val.0.to_string().replace('"', ""),
-                parse_tallyvotes_ratios(val.1.to_string().replace('"', "")),
-            )
-        })
-        .collect();
-    hmap.remove_entry("proposal").unwrap();
-    hmap.remove_entry("blocks_counted").unwrap();
-    hmap.remove_entry("height_start").unwrap();
-    hmap.remove_entry("height_end").unwrap();
-    Ok(hmap)
+    let total = |key: &str| {
+        rawmap.get(key).and_then(Value::as_u64).ok_or(format!(
+            "tallyvotes of proposal {} lacks {}.",
+            proposal_id, key
+        ))
+    };
+    let mut options: Vec<OptionStats> = Vec::new();
+    for (key, value) in rawmap.iter() {
+        match (key.strip_prefix("Option "), value.as_str()) {
+            (Some(option), Some(ratio)) => {
+                let (blocks, share) = parse_tallyvotes_ratios(ratio).ok_or(format!(
+                    "Malformed {} in tallyvotes of proposal {}.",
+                    key, proposal_id
+                ))?;
+                options.push(OptionStats {
+                    option: option.parse::<u64>()?,
+                    label: None,
+                    blocks,
+                    share,
+                    weight: None,
+                    weight_share: None,
+                });
+            }
+            _ if ["proposal", "height_start", "height_end", "blocks_counted"]
+                .contains(&key.as_str()) => {}
+            _ => warn!(
+                "Unexpected {} in tallyvotes of proposal {}.",
+                key, proposal_id
+            ),
+        }
+    }
+    options.sort_by_key(|option| option.option);
+    Ok(Proposal {
+        proposal_id,
+        height_start: total("height_start")?,
+        height: total("height_end")?,
+        blocks_counted: total("blocks_counted")?,
+        blocks_voted: options.iter().map(|option| option.blocks).sum(),
+        weight: None,
+        options,
+        participation: None,
+    })
 }
 
-fn parse_tallyvotes_ratios(raw: String) -> (u64, f64) {
-    let vote_stats_iterator = raw.split(", ");
-    let mut index = 0;
-    let mut vote_args_tuple: (u64, f64) = (0, 0.0);
-    for vote_stat in vote_stats_iterator {
-        if index == 0 {
-            vote_args_tuple.0 = vote_stat.replace("%", "").trim().parse::<u64>().unwrap();
-        } else if index == 1 {
-            vote_args_tuple.1 = vote_stat.replace("%", "").trim().parse::<f64>().unwrap();
-        }
-        index += 1;
+// Percentages are kept in basis points, so '45.67%' becomes 4567. Further places are rounded.
+pub fn basis_points(percentage: &str) -> Option<u64> {
+    let (whole, fraction) = percentage.split_once('.').unwrap_or((percentage, ""));
+    if !fraction.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
     }
-    return vote_args_tuple;
+    let place = |i: usize| {
+        fraction
+            .as_bytes()
+            .get(i)
+            .map_or(0, |b| u64::from(b - b'0'))
+    };
+    let rounding = u64::from(place(2) >= 5);
+    whole
+        .parse::<u64>()
+        .ok()?
+        .checked_mul(100)?
+        .checked_add(place(0) * 10 + place(1) + rounding)
+}
+
+// Ratios of tallyvotes look like '12, 45.67%': blocks voted and their share of the range.
+pub fn parse_tallyvotes_ratios(raw: &str) -> Option<(u64, u64)> {
+    let (blocks, percentage) = raw.split_once(", ")?;
+    let percentage = percentage.trim().strip_suffix('%').unwrap_or(percentage);
+    Some((
+        blocks.trim().parse().ok()?,
+        basis_points(percentage.trim())?,
+    ))
 }
 
 pub async fn getblockhash(height: u64, rpcurl: &RPCURL) -> Result<String, Box<dyn Error>> {
@@ -347,39 +516,204 @@ pub async fn getblockhash(height: u64, rpcurl: &RPCURL) -> Result<String, Box<dy
     Ok(hash)
 }
 
+// Fields which particld reports in coins.
+const BLOCK_COINS: &[&str] = &["stakekernelvalue"];
+const VOUT_COINS: &[&str] = &["value", "smsgfeerate", "treasury_fund_cfwd", "ct_fee"];
+
+pub fn tx_coins_to_sats(tx: &mut Value) {
+    if let Some(vouts) = tx.get_mut("vout").and_then(Value::as_array_mut) {
+        for vout in vouts.iter_mut() {
+            amount::coins_to_sats(vout, VOUT_COINS);
+        }
+    }
+}
+
+pub fn block_coins_to_sats(block: &mut Value) {
+    amount::coins_to_sats(block, BLOCK_COINS);
+    if let Some(txs) = block.get_mut("tx").and_then(Value::as_array_mut) {
+        txs.iter_mut().for_each(tx_coins_to_sats);
+    }
+}
+
 pub async fn getblock(
     blockhash: impl Into<String>,
     db: &Surreal<Client>,
     rpcurl: &RPCURL,
 ) -> Result<BlockData, Box<dyn Error>> {
     let arg = format!("getblock {} 2 true", blockhash.into());
-    let value = call(&arg, rpcurl)?;
+    let mut value = call(&arg, rpcurl)?;
+    block_coins_to_sats(&mut value);
     let mut blockdata: BlockData = serde_json::from_value(value)?;
+    blockdata.classify_scripts();
     blockdata.determine_coldstaking(db, rpcurl).await?;
     blockdata.read_vote();
     Ok(blockdata)
 }
 
-pub async fn getnewproposal(
-    blockdata: &BlockData,
-    proposal_ids: &Vec<u64>,
-    rpcurl: &RPCURL,
-) -> Result<Option<Proposal>, Box<dyn Error>> {
-    // 616959 is the block at which the first vote was recorded. Vote must have an associated proposal.
-    if blockdata.height >= 616959 {
-        match blockdata.voting_info.clone() {
-            Some(vote) => {
-                let existsyet = proposal_ids.iter().any(|&x| x == vote.proposal_id);
-                if !existsyet {
-                    let proposal = vote.gen_proposal(rpcurl).await?;
-                    Ok(Some(proposal))
-                } else {
-                    Ok(None)
-                }
-            }
-            _ => Ok(None),
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // Transaction as reported by particld.
+    pub fn tx(txid: &str, vin: Value, vout: Value) -> Value {
+        json!({
+            "txid": txid,
+            "hash": txid,
+            "version": 160,
+            "size": 200,
+            "vsize": 200,
+            "weight": 800,
+            "locktime": 0,
+            "hex": "",
+            "vin": vin,
+            "vout": vout,
+        })
+    }
+
+    pub fn standard_vin(txid: &str, vout: u64) -> Value {
+        json!({"txid": txid, "vout": vout, "scriptSig": {"asm": "", "hex": ""}})
+    }
+
+    pub fn standard_vout(n: u64, address: &str, valuesat: u64) -> Value {
+        json!({
+            "n": n,
+            "type": "standard",
+            "value": valuesat as f64 / 1e8,
+            "valueSat": valuesat,
+            "scriptPubKey": {"asm": "", "hex": "", "type": "pubkeyhash", "addresses": [address]},
+        })
+    }
+
+    // Block of the given transactions as getblock returns it, amounts converted to satoshis.
+    pub fn blockdata(height: u64, txs: Vec<Value>) -> BlockData {
+        let mut block = json!({
+            "bits": "1a0fffff",
+            "chainwork": "00",
+            "difficulty": 1.0,
+            "hash": format!("{:064x}", height),
+            "height": height,
+            "mediantime": 1_600_000_000 + height * 120,
+            "merkleroot": "",
+            "nTx": txs.len(),
+            "nonce": 0,
+            "size": 0,
+            "strippedsize": 0,
+            "time": 1_600_000_000 + height * 120,
+            "tx": txs,
+            "version": 536870912,
+            "versionHex": "20000000",
+            "weight": 0,
+            "witnessmerkleroot": "",
+        });
+        block_coins_to_sats(&mut block);
+        serde_json::from_value(block).unwrap()
+    }
+
+    #[test]
+    fn parse_vote_accepts_only_two_numbers() {
+        let cases = [
+            ("12, 1", Some((12, 1))),
+            ("0, 0", Some((0, 0))),
+            ("12,1", None),
+            ("12, one", None),
+            ("12, -1", None),
+            ("12", None),
+            ("1, 2, 3", None),
+            ("", None),
+        ];
+        for (content, expected) in cases {
+            let parsed = parse_vote(content).map(|vote| (vote.proposal_id, vote.voted_for_option));
+            assert_eq!(parsed, expected, "{}", content);
+        }
+    }
+
+    #[test]
+    fn basis_points_round_and_reject_garbage() {
+        let cases = [
+            ("45.67", Some(4567)),
+            ("45.6", Some(4560)),
+            ("45", Some(4500)),
+            ("0.00", Some(0)),
+            ("100.00", Some(10000)),
+            ("45.675", Some(4568)),
+            ("45.674", Some(4567)),
+            ("99.995", Some(10000)),
+            ("", None),
+            ("-1.00", None),
+            ("45.6x", None),
+            ("4x.00", None),
+            ("nan", None),
+        ];
+        for (percentage, expected) in cases {
+            assert_eq!(basis_points(percentage), expected, "{}", percentage);
+        }
+    }
+
+    #[test]
+    fn tallyvotes_ratios() {
+        let cases = [
+            ("12, 45.67%", Some((12, 4567))),
+            ("0, 0.00%", Some((0, 0))),
+            ("12, 45.67", Some((12, 4567))),
+            ("12", None),
+            ("x, 45.67%", None),
+            ("12, %", None),
+        ];
+        for (raw, expected) in cases {
+            assert_eq!(parse_tallyvotes_ratios(raw), expected, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn stored_vouts_read_back_unchanged() {
+        let mut tx = json!({"vout": [
+            {
+                "n": 0,
+                "type": "data",
+                "data_hex": "",
+                "smsgfeerate": 0.00005,
+                "treasury_fund_cfwd": 12.5,
+                "ct_fee": 0.00004,
+            },
+            {
+                "n": 1,
+                "type": "standard",
+                "value": 1.00000001,
+                "valueSat": 100000001,
+                "scriptPubKey": {"asm": "", "hex": "", "type": "pubkeyhash"},
+            },
+            {
+                "n": 2,
+                "type": "blind",
+                "valueCommitment": "08aa",
+                "data_hex": "02bb",
+                "rangeproof": "cc",
+            },
+            {
+                "n": 3,
+                "type": "anon",
+                "pubkey": "03dd",
+                "valueCommitment": "09aa",
+                "data_hex": "02bb",
+                "rangeproof": "cc",
+            },
+        ]});
+        tx_coins_to_sats(&mut tx);
+        let vouts: Vec<Vout> = serde_json::from_value(tx["vout"].clone()).unwrap();
+        assert!(matches!(vouts[0], Vout::Data { .. }), "{:?}", vouts[0]);
+        match &vouts[1] {
+            Vout::Standard {
+                value, valuesat, ..
+            } => assert_eq!(value.sat(), *valuesat),
+            vout => panic!("{:?}", vout),
         }
-    } else {
-        Ok(None)
+        assert!(matches!(vouts[2], Vout::Blind { .. }), "{:?}", vouts[2]);
+        assert!(matches!(vouts[3], Vout::Anon { .. }), "{:?}", vouts[3]);
+        let stored = serde_json::to_value(&vouts).unwrap();
+        let reread: Vec<Vout> = serde_json::from_value(stored.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&reread).unwrap(), stored);
+        assert_eq!(stored[0]["ct_fee"], 4000);
+        assert_eq!(stored[0]["treasury_fund_cfwd"], 1_250_000_000);
     }
 }
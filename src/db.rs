@@ -1,9 +1,27 @@
 use crate::{
-    console::{BlockData, Proposal, Stakeaddress},
+    args::Settings,
+    balances::Balance,
+    console::{BlockData, Proposal, Stakeaddress, UnknownShape},
+    decentralization::Metrics,
+    delegations::DelegationChange,
     engine::ProcessedBlocks,
+    fees::{BlockFees, Fee},
+    migrations::{LegacyTally, Migration, RecordedBlock, RingTx, SchemaVersion},
+    privacy::PrivacyStats,
+    registry::{Lifecycle, ProposalMeta},
+    rings::{RingInput, RingStats},
+    smsg::SmsgParams,
+    stakers::Stake,
+    storage::{self, StorageProfile},
+    tally::OptionVotes,
+    treasury::TreasuryEntry,
+    turnout::BlockTurnout,
+    utxo::{Spend, Utxo},
+    votes::{VoteRecord, VotedBlock},
 };
 use clap::ArgMatches;
-use std::{error::Error, time::Duration};
+use serde_json::Value;
+use std::{collections::HashMap, error::Error, time::Duration};
 use surrealdb::{
     engine::remote::ws::{Client, Ws},
     Surreal,
@@ -39,6 +57,57 @@ pub async fn init(args: &ArgMatches) -> Surreal<Client> {
     }
 }
 
+pub async fn getschemaversion(db: &Surreal<Client>) -> Result<u64, Box<dyn Error>> {
+    trace!("Querying schema version ...");
+    let recorded: Option<SchemaVersion> = db.select(("schema_version", "current")).await?;
+    if let Some(recorded) = recorded {
+        return Ok(recorded.version);
+    }
+    // Stages which applied the schema before migrations existed recorded it in schema:version.
+    // Its version 1 matches migration 1, and the record is removed once a migration is recorded.
+    let legacy: Option<SchemaVersion> = db.select(("schema", "version")).await?;
+    Ok(legacy.map_or(0, |legacy| legacy.version))
+}
+
+// Runs a SurrealQL migration and records its version in the same transaction.
+pub async fn regquerymigration(
+    db: &Surreal<Client>,
+    migration: &Migration,
+    query: &str,
+) -> Result<(), Box<dyn Error>> {
+    db.query("BEGIN TRANSACTION")
+        .query(query)
+        .query(SCHEMA_VERSION_UPSERT)
+        .query("DELETE schema:version")
+        .query("COMMIT TRANSACTION")
+        .bind(("version", migration.version))
+        .bind(("description", migration.description))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn regschemaversion(
+    db: &Surreal<Client>,
+    migration: &Migration,
+) -> Result<(), Box<dyn Error>> {
+    db.query(SCHEMA_VERSION_UPSERT)
+        .query("DELETE schema:version")
+        .bind(("version", migration.version))
+        .bind(("description", migration.description))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+const SCHEMA_VERSION_UPSERT: &str = "UPSERT schema_version:current SET
+    version = $version,
+    history = array::append(history ?? [], {
+        version: $version,
+        description: $description,
+        applied: time::now(),
+    })";
+
 // Sum heights from bottom to top both
 // mathematically and via SQL to ensure data consistency
 pub async fn toprec(db: &Surreal<Client>) -> Result<Option<u64>, Box<dyn Error>> {
@@ -60,7 +129,7 @@ pub async fn toprec(db: &Surreal<Client>) -> Result<Option<u64>, Box<dyn Error>>
                     min_height
                 );
             }
-            let fold: u64 = (min_height..=top_height).fold(0, |acc, x| acc + x);
+            let fold: u64 = (min_height..=top_height).sum();
             let dbfold: u64 = response.take::<Option<u64>>(3)?.unwrap();
             if fold != dbfold {
                 error!(
@@ -105,24 +174,179 @@ pub async fn regtrackedzmq(
     Ok(())
 }
 
-pub async fn regblock(db: &Surreal<Client>, blockdata: &BlockData) -> Result<(), Box<dyn Error>> {
+pub async fn regblock(
+    db: &Surreal<Client>,
+    blockdata: &BlockData,
+    profile: StorageProfile,
+) -> Result<(), Box<dyn Error>> {
     info!("Registering block {} into DB ...", blockdata.height);
-    let _: Option<BlockData> = db
-        .create(("blocks", blockdata.height as i64))
-        .content(blockdata.clone())
-        .await?;
+    db.query("CREATE type::thing('blocks', $height) CONTENT $block RETURN NONE")
+        .bind(("height", blockdata.height as i64))
+        .bind(("block", storage::prune(blockdata, profile)?))
+        .await?
+        .check()?;
     Ok(())
 }
 
+// Upserts the latest stats and keeps every tally in the history.
 pub async fn regproposal(db: &Surreal<Client>, proposal: &Proposal) -> Result<(), Box<dyn Error>> {
     info!(
-        "Registering proposal ID {} into DB ...",
-        proposal.proposal_id
+        "Registering tally of proposal ID {} at height {} into DB ...",
+        proposal.proposal_id, proposal.height
     );
-    let _: Option<Proposal> = db
-        .create(("proposals", proposal.proposal_id as i64))
-        .content(proposal.clone())
+    // Merged, so metadata and lifecycle state recorded from the registry are kept.
+    db.query("UPSERT type::thing('proposals', $proposal.proposal_id) MERGE $proposal RETURN NONE")
+        .query(
+            "UPSERT type::thing('tallies', [$proposal.proposal_id, $proposal.height])
+                CONTENT $proposal RETURN NONE",
+        )
+        .bind(("proposal", proposal.clone()))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn regproposalstate(
+    db: &Surreal<Client>,
+    meta: &ProposalMeta,
+    state: Lifecycle,
+    height: u64,
+) -> Result<(), Box<dyn Error>> {
+    trace!(
+        "Proposal {} is {:?} at height {}.",
+        meta.proposal_id,
+        state,
+        height
+    );
+    db.query(
+        "UPSERT type::thing('proposals', $meta.proposal_id) SET
+            proposal_id = $meta.proposal_id,
+            metadata = $meta,
+            state = $state,
+            state_height = $height
+            RETURN NONE",
+    )
+    // Through JSON, so option labels keyed by numbers become object keys.
+    .bind(("meta", serde_json::to_value(meta)?))
+    .bind(("state", state))
+    .bind(("height", height))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+// Votes for every option of the proposal within the given range of heights.
+pub async fn getoptionvotes(
+    db: &Surreal<Client>,
+    proposal_id: u64,
+    from_height: u64,
+    to_height: u64,
+) -> Result<Vec<OptionVotes>, Box<dyn Error>> {
+    trace!("Counting votes for proposal {} ...", proposal_id);
+    let mut response = db
+        .query(
+            "SELECT option, count() AS votes, math::sum(weight) AS weight FROM votes
+                WHERE proposal_id = $proposal_id AND height >= $from AND height <= $to
+                GROUP BY option",
+        )
+        .bind(("proposal_id", proposal_id))
+        .bind(("from", from_height))
+        .bind(("to", to_height))
+        .await?;
+    let votes: Vec<OptionVotes> = response.take(0)?;
+    Ok(votes)
+}
+
+// Tallies recorded before option stats were typed.
+// Untyped stats of tallies, oldest first, followed by those of proposals, which are the latest.
+// Proposals re-tallied by migration 2 have no height, so the given one is assumed.
+pub async fn getlegacytallies(
+    db: &Surreal<Client>,
+    height: u64,
+) -> Result<Vec<LegacyTally>, Box<dyn Error>> {
+    trace!("Querying tallies and proposals with untyped stats ...");
+    let mut response = db
+        .query("SELECT proposal_id, height, stats FROM tallies WHERE stats != NONE ORDER BY height")
+        .query(
+            "SELECT proposal_id, height ?? $height AS height, stats FROM proposals
+                WHERE stats != NONE",
+        )
+        .bind(("height", height))
+        .await?;
+    let mut tallies: Vec<LegacyTally> = response.take(0)?;
+    let proposals: Vec<LegacyTally> = response.take(1)?;
+    tallies.extend(proposals);
+    Ok(tallies)
+}
+
+pub async fn unsetlegacystats(db: &Surreal<Client>) -> Result<(), Box<dyn Error>> {
+    db.query("UPDATE proposals, tallies UNSET stats WHERE stats != NONE")
+        .await?
+        .check()?;
+    Ok(())
+}
+
+// Transactions with anon inputs recorded before key images were decoded.
+pub async fn getringtxswithoutkeyimages(
+    db: &Surreal<Client>,
+) -> Result<Vec<RingTx>, Box<dyn Error>> {
+    trace!("Querying anon inputs without key images ...");
+    let mut response = db
+        .query("SELECT txid, height FROM rings WHERE key_images = NONE GROUP BY txid, height")
         .await?;
+    let txs: Vec<RingTx> = response.take(0)?;
+    Ok(txs)
+}
+
+pub async fn regkeyimages(
+    db: &Surreal<Client>,
+    txid: &str,
+    key_images: &[Vec<String>],
+) -> Result<(), Box<dyn Error>> {
+    trace!("Recording key images of {} ...", txid);
+    db.query(
+        "FOR $input IN (SELECT VALUE input FROM rings WHERE txid = $txid) {
+            UPDATE type::thing('rings', [$txid, $input]) SET key_images = $key_images[$input];
+        }",
+    )
+    .bind(("txid", txid.to_string()))
+    .bind(("key_images", key_images.to_vec()))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+// Proposals which received a vote within the given range of heights.
+pub async fn getvotedproposals(
+    db: &Surreal<Client>,
+    from_height: u64,
+    to_height: u64,
+) -> Result<Vec<u64>, Box<dyn Error>> {
+    trace!(
+        "Querying proposals voted on from height {} to {} ...",
+        from_height,
+        to_height
+    );
+    let mut response = db
+        .query("array::distinct(SELECT VALUE proposal_id FROM votes WHERE height >= $from AND height <= $to)")
+        .bind(("from", from_height))
+        .bind(("to", to_height))
+        .await?;
+    let proposal_ids: Vec<u64> = response.take(0)?;
+    Ok(proposal_ids)
+}
+
+pub async fn regproposalstats(
+    db: &Surreal<Client>,
+    proposal_id: u64,
+    stats: &HashMap<String, (u64, u64)>,
+) -> Result<(), Box<dyn Error>> {
+    trace!("Updating stats of proposal ID {} ...", proposal_id);
+    db.query("UPDATE type::thing('proposals', $proposal_id) SET stats = $stats RETURN NONE")
+        .bind(("proposal_id", proposal_id))
+        .bind(("stats", stats.clone()))
+        .await?
+        .check()?;
     Ok(())
 }
 
@@ -136,10 +360,729 @@ pub async fn regstakeaddress(
     db: &Surreal<Client>,
     stakeaddr: &Stakeaddress,
 ) -> Result<(), Box<dyn Error>> {
-    trace!("Recording new stakeaddress into DB ...");
+    trace!("Recording stakeaddress into DB ...");
     let _: Option<Stakeaddress> = db
-        .create(("stakeaddresses", stakeaddr.raw.clone()))
+        .upsert(("stakeaddresses", stakeaddr.raw.clone()))
         .content(stakeaddr.clone())
         .await?;
     Ok(())
 }
+
+// Distinct stakeaddresses of recorded cold-staking outputs with their stake-only address.
+pub async fn getcoldstakeaddresses(
+    db: &Surreal<Client>,
+) -> Result<Vec<(String, String)>, Box<dyn Error>> {
+    trace!("Querying stakeaddresses of cold-staking outputs ...");
+    let mut response = db
+        .query(
+            "array::distinct(SELECT VALUE [stakeaddresses[0], coldstake.stakeonly] FROM utxos
+                WHERE coldstake != NONE AND stakeaddresses[0] != NONE)",
+        )
+        .await?;
+    let addresses: Vec<(String, String)> = response.take(0)?;
+    Ok(addresses)
+}
+
+pub async fn getblockhash(
+    db: &Surreal<Client>,
+    height: u64,
+) -> Result<Option<String>, Box<dyn Error>> {
+    trace!("Querying recorded hash of block {} ...", height);
+    let mut response = db
+        .query("SELECT VALUE hash FROM type::thing('blocks', $height)")
+        .bind(("height", height as i64))
+        .await?;
+    let hash: Option<String> = response.take(0)?;
+    Ok(hash)
+}
+
+// Removes everything recorded at or above the given height and marks
+// the outputs spent in those blocks as unspent again.
+pub async fn rollback(
+    db: &Surreal<Client>,
+    height: u64,
+    settings: &Settings,
+) -> Result<(), Box<dyn Error>> {
+    debug!("Rolling back records from height {} ...", height);
+    db.query("BEGIN TRANSACTION")
+        .query("DELETE blocks WHERE height >= $height")
+        .query("DELETE utxos WHERE height >= $height")
+        .query("UPDATE utxos SET spent = NONE WHERE spent.height >= $height")
+        .query("DELETE fees WHERE height >= $height")
+        .query("DELETE blockfees WHERE height >= $height")
+        .query("DELETE treasury WHERE height >= $height")
+        .query("DELETE smsg WHERE height >= $height")
+        .query(
+            "FOR $stats IN (SELECT * FROM privacy WHERE height >= $height) {
+                UPDATE type::thing('privacydaily', $stats.day) SET
+                    blocks -= 1,
+                    standard_outputs -= $stats.standard_outputs,
+                    blind_outputs -= $stats.blind_outputs,
+                    anon_outputs -= $stats.anon_outputs,
+                    rangeproofs -= $stats.rangeproofs,
+                    rangeproof_bytes -= $stats.rangeproof_bytes,
+                    standard_txs -= $stats.standard_txs,
+                    blind_txs -= $stats.blind_txs,
+                    anon_txs -= $stats.anon_txs;
+            }",
+        )
+        .query("DELETE privacy WHERE height >= $height")
+        .query("DELETE privacydaily WHERE blocks = 0")
+        .query("DELETE rings WHERE height >= $height")
+        .query("DELETE ringblocks WHERE height >= $height")
+        .query("UPDATE counters:unknownshapes SET total -= count(SELECT id FROM unknownshapes WHERE height >= $height)")
+        .query("DELETE unknownshapes WHERE height >= $height")
+        .query("LET $pairs = array::distinct(SELECT VALUE [spend, stakeonly] FROM delegationchanges WHERE height >= $height)")
+        .query("DELETE delegationchanges WHERE height >= $height")
+        .query(
+            "FOR $pair IN $pairs {
+                LET $changes = SELECT * FROM delegationchanges WHERE spend = $pair[0] AND stakeonly = $pair[1];
+                IF count($changes) = 0 {
+                    DELETE type::thing('delegations', $pair);
+                } ELSE {
+                    UPDATE type::thing('delegations', $pair) SET
+                        first_height = math::min($changes.height),
+                        last_height = math::max(SELECT VALUE height FROM $changes WHERE outputs > 0),
+                        outputs = math::sum($changes.outputs),
+                        value = math::sum($changes.delta);
+                };
+            }",
+        )
+        .query("LET $addresses = array::distinct(SELECT VALUE address FROM balancehistory WHERE height >= $height)")
+        .query("DELETE balancehistory WHERE height >= $height")
+        .query(
+            "FOR $address IN $addresses {
+                LET $last = (SELECT address, height, balance, delta FROM balancehistory
+                    WHERE address = $address ORDER BY height DESC LIMIT 1)[0];
+                IF $last = NONE {
+                    DELETE type::thing('balances', $address);
+                } ELSE {
+                    UPSERT type::thing('balances', $address) CONTENT $last;
+                };
+            }",
+        )
+        // Stakers which had a block within the window of any rolled back block.
+        .query("LET $stakers = array::distinct(SELECT VALUE staker FROM stakes WHERE height >= $height - $window)")
+        .query("DELETE stakes WHERE height >= $height")
+        .query(
+            "FOR $staker IN $stakers {
+                LET $stakes = SELECT height, reward FROM stakes WHERE staker = $staker;
+                IF count($stakes) = 0 {
+                    DELETE type::thing('stakers', $staker);
+                } ELSE {
+                    LET $recent = count(SELECT height FROM $stakes WHERE height > $height - 1 - $window);
+                    UPDATE type::thing('stakers', $staker) SET
+                        blocks = count($stakes),
+                        reward = math::sum($stakes.reward),
+                        first_height = math::min($stakes.height),
+                        last_height = math::max($stakes.height),
+                        recent_blocks = $recent,
+                        recent_share = <float> $recent / $window,
+                        window = $window;
+                };
+            }",
+        )
+        .query("DELETE decentralization WHERE height >= $height")
+        .query("LET $days = array::distinct(SELECT VALUE day FROM turnout WHERE height >= $height)")
+        .query("DELETE turnout WHERE height >= $height")
+        .query(
+            "FOR $day IN $days {
+                LET $blocks = SELECT staked, voted, proposal_id FROM turnout WHERE day = $day;
+                IF count($blocks) = 0 {
+                    DELETE type::thing('turnoutdaily', $day);
+                } ELSE {
+                    LET $staked = count($blocks[WHERE staked = true]);
+                    LET $voted = count($blocks[WHERE voted = true]);
+                    UPDATE type::thing('turnoutdaily', $day) SET
+                        blocks = count($blocks),
+                        staked_blocks = $staked,
+                        voted_blocks = $voted,
+                        proposal_ids = array::distinct($blocks[WHERE proposal_id != NONE].proposal_id),
+                        proposals = count(proposal_ids),
+                        turnout = IF $staked > 0 THEN <int> math::floor(<float> $voted * 10000 / $staked) ELSE 0 END;
+                };
+            }",
+        )
+        .query("LET $voters = array::distinct(SELECT VALUE [proposal_id, staker] FROM votes WHERE height >= $height AND staker != NONE)")
+        .query("DELETE votes WHERE height >= $height")
+        .query("DELETE votechanges WHERE height >= $height")
+        .query(
+            "FOR $voter IN $voters {
+                LET $votes = SELECT height, option, weight ?? 0 AS weight FROM votes
+                    WHERE proposal_id = $voter[0] AND staker = $voter[1] ORDER BY height;
+                IF count($votes) = 0 {
+                    DELETE type::thing('voters', $voter);
+                } ELSE {
+                    UPDATE type::thing('voters', $voter) SET
+                        option = array::last($votes).option,
+                        blocks = count($votes),
+                        weight = math::sum($votes.weight),
+                        changes = count(SELECT height FROM votechanges WHERE proposal_id = $voter[0] AND staker = $voter[1]),
+                        first_height = math::min($votes.height),
+                        last_height = math::max($votes.height);
+                };
+            }",
+        )
+        .query("DELETE tallies WHERE height >= $height")
+        .query("UPDATE proposals SET state = NONE, state_height = NONE WHERE state_height >= $height")
+        .query(
+            "FOR $proposal IN (SELECT proposal_id FROM proposals WHERE height >= $height) {
+                LET $last = (SELECT * OMIT id FROM tallies
+                    WHERE proposal_id = $proposal.proposal_id ORDER BY height DESC LIMIT 1)[0];
+                IF $last = NONE {
+                    DELETE type::thing('proposals', $proposal.proposal_id);
+                } ELSE {
+                    UPSERT type::thing('proposals', $proposal.proposal_id) MERGE $last;
+                };
+            }",
+        )
+        .query("COMMIT TRANSACTION")
+        .bind(("height", height))
+        .bind(("window", settings.leaderboard_window))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn regutxos(db: &Surreal<Client>, utxos: &[Utxo]) -> Result<(), Box<dyn Error>> {
+    trace!("Recording {} new outputs ...", utxos.len());
+    if utxos.is_empty() {
+        return Ok(());
+    }
+    db.query("FOR $utxo IN $utxos { CREATE type::thing('utxos', $utxo.outpoint) CONTENT $utxo }")
+        .bind(("utxos", utxos.to_vec()))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+// Any recorded output, to tell whether outputs were tracked while the blocks were indexed.
+pub async fn getanyutxo(db: &Surreal<Client>) -> Result<Option<String>, Box<dyn Error>> {
+    let mut response = db.query("SELECT VALUE outpoint FROM utxos LIMIT 1").await?;
+    let outpoints: Vec<String> = response.take(0)?;
+    Ok(outpoints.into_iter().next())
+}
+
+// Marks outputs as spent and returns them as they were before spending.
+pub async fn spendutxos(
+    db: &Surreal<Client>,
+    spends: &[Spend],
+) -> Result<Vec<Utxo>, Box<dyn Error>> {
+    trace!("Marking {} outputs as spent ...", spends.len());
+    if spends.is_empty() {
+        return Ok(Vec::new());
+    }
+    let statements: String = (0..spends.len())
+        .map(|i| {
+            format!(
+                "UPDATE type::thing('utxos', $spends[{0}].outpoint) SET spent = $spends[{0}].spent RETURN BEFORE;",
+                i
+            )
+        })
+        .collect();
+    let mut response = db
+        .query(statements)
+        .bind(("spends", spends.to_vec()))
+        .await?;
+    let mut previous = Vec::with_capacity(spends.len());
+    for (i, spend) in spends.iter().enumerate() {
+        let utxo: Option<Utxo> = response.take(i)?;
+        match utxo {
+            Some(utxo) => previous.push(utxo),
+            None => warn!(
+                "Output {} is unknown, can't mark it as spent.",
+                spend.outpoint
+            ),
+        }
+    }
+    Ok(previous)
+}
+
+pub async fn getbalances(
+    db: &Surreal<Client>,
+    addresses: Vec<String>,
+) -> Result<Vec<Balance>, Box<dyn Error>> {
+    trace!("Querying balances of {} addresses ...", addresses.len());
+    let ids: Vec<RecordId> = addresses
+        .into_iter()
+        .map(|address| RecordId::from(("balances", address)))
+        .collect();
+    let mut response = db
+        .query("SELECT address, height, balance, delta FROM $ids")
+        .bind(("ids", ids))
+        .await?;
+    let balances: Vec<Balance> = response.take(0)?;
+    Ok(balances)
+}
+
+pub async fn regbalances(db: &Surreal<Client>, balances: &[Balance]) -> Result<(), Box<dyn Error>> {
+    trace!("Recording {} balance changes ...", balances.len());
+    if balances.is_empty() {
+        return Ok(());
+    }
+    db.query(
+        "FOR $balance IN $balances {
+            UPSERT type::thing('balances', $balance.address) CONTENT $balance;
+            CREATE type::thing('balancehistory', [$balance.address, $balance.height]) CONTENT $balance;
+        }",
+    )
+    .bind(("balances", balances.to_vec()))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+pub async fn regfees(
+    db: &Surreal<Client>,
+    fees: &[Fee],
+    blockfees: &BlockFees,
+) -> Result<(), Box<dyn Error>> {
+    trace!("Recording fees of {} transactions ...", fees.len());
+    db.query("FOR $fee IN $fees { CREATE type::thing('fees', $fee.txid) CONTENT $fee }")
+        .query("CREATE type::thing('blockfees', $blockfees.height) CONTENT $blockfees")
+        .bind(("fees", fees.to_vec()))
+        .bind(("blockfees", blockfees.clone()))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn gettreasury(
+    db: &Surreal<Client>,
+    height: u64,
+) -> Result<Option<TreasuryEntry>, Box<dyn Error>> {
+    trace!("Querying treasury ledger at height {} ...", height);
+    let entry: Option<TreasuryEntry> = db.select(("treasury", height as i64)).await?;
+    Ok(entry)
+}
+
+pub async fn regtreasury(
+    db: &Surreal<Client>,
+    entry: &TreasuryEntry,
+) -> Result<(), Box<dyn Error>> {
+    trace!("Recording treasury ledger entry ...");
+    let _: Option<TreasuryEntry> = db
+        .create(("treasury", entry.height as i64))
+        .content(entry.clone())
+        .await?;
+    Ok(())
+}
+
+pub async fn getsmsg(
+    db: &Surreal<Client>,
+    height: u64,
+) -> Result<Option<SmsgParams>, Box<dyn Error>> {
+    trace!("Querying SMSG parameters at height {} ...", height);
+    let params: Option<SmsgParams> = db.select(("smsg", height as i64)).await?;
+    Ok(params)
+}
+
+pub async fn regsmsg(db: &Surreal<Client>, params: &SmsgParams) -> Result<(), Box<dyn Error>> {
+    trace!("Recording SMSG parameters ...");
+    let _: Option<SmsgParams> = db
+        .create(("smsg", params.height as i64))
+        .content(params.clone())
+        .await?;
+    Ok(())
+}
+
+pub async fn regprivacy(db: &Surreal<Client>, stats: &PrivacyStats) -> Result<(), Box<dyn Error>> {
+    trace!("Recording privacy statistics ...");
+    db.query("CREATE type::thing('privacy', $stats.height) CONTENT $stats")
+        .query(
+            "UPSERT type::thing('privacydaily', $stats.day) SET
+                day = $stats.day,
+                blocks += 1,
+                standard_outputs += $stats.standard_outputs,
+                blind_outputs += $stats.blind_outputs,
+                anon_outputs += $stats.anon_outputs,
+                rangeproofs += $stats.rangeproofs,
+                rangeproof_bytes += $stats.rangeproof_bytes,
+                standard_txs += $stats.standard_txs,
+                blind_txs += $stats.blind_txs,
+                anon_txs += $stats.anon_txs",
+        )
+        .bind(("stats", stats.clone()))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn regrings(
+    db: &Surreal<Client>,
+    inputs: &[RingInput],
+    stats: &RingStats,
+) -> Result<(), Box<dyn Error>> {
+    trace!("Recording {} anon inputs ...", inputs.len());
+    db.query("FOR $input IN $inputs { CREATE type::thing('rings', [$input.txid, $input.input]) CONTENT $input }")
+        .query("CREATE type::thing('ringblocks', $stats.height) CONTENT $stats")
+        .bind(("inputs", inputs.to_vec()))
+        .bind(("stats", stats.clone()))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn regunknownshapes(
+    db: &Surreal<Client>,
+    shapes: &[UnknownShape],
+) -> Result<u64, Box<dyn Error>> {
+    trace!(
+        "Recording {} inputs and outputs of unknown shape ...",
+        shapes.len()
+    );
+    let mut response = db
+        .query("FOR $shape IN $shapes { CREATE unknownshapes CONTENT $shape }")
+        .query("UPSERT counters:unknownshapes SET total += $count RETURN VALUE total")
+        .bind(("shapes", shapes.to_vec()))
+        .bind(("count", shapes.len() as u64))
+        .await?
+        .check()?;
+    let total: Option<u64> = response.take(1)?;
+    Ok(total.unwrap_or(0))
+}
+
+pub async fn regdelegations(
+    db: &Surreal<Client>,
+    changes: &[DelegationChange],
+) -> Result<(), Box<dyn Error>> {
+    trace!("Recording {} delegation changes ...", changes.len());
+    if changes.is_empty() {
+        return Ok(());
+    }
+    db.query(
+        "FOR $change IN $changes {
+            CREATE type::thing('delegationchanges', [$change.spend, $change.stakeonly, $change.height])
+                CONTENT $change;
+            UPSERT type::thing('delegations', [$change.spend, $change.stakeonly]) SET
+                spend = $change.spend,
+                stakeonly = $change.stakeonly,
+                pool = $change.pool,
+                first_height = first_height ?? $change.height,
+                last_height = IF $change.outputs > 0 THEN $change.height ELSE last_height END,
+                outputs += $change.outputs,
+                value += $change.delta;
+        }",
+    )
+    .bind(("changes", changes.to_vec()))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+// Updates the staker of the block and the one whose block left the recent window.
+pub async fn regstake(
+    db: &Surreal<Client>,
+    stake: &Stake,
+    window: u64,
+) -> Result<(), Box<dyn Error>> {
+    trace!("Recording block staked by {} ...", stake.staker);
+    db.query("CREATE type::thing('stakes', $stake.height) CONTENT $stake")
+        .query(
+            "UPSERT type::thing('stakers', $stake.staker) SET
+                staker = $stake.staker,
+                kind = $stake.kind,
+                blocks += 1,
+                reward += $stake.reward,
+                first_height = first_height ?? $stake.height,
+                last_height = $stake.height,
+                recent_blocks += 1,
+                recent_share = <float> recent_blocks / $window,
+                window = $window",
+        )
+        .query(
+            "LET $leaving = (SELECT staker FROM type::thing('stakes', $stake.height - $window))[0]",
+        )
+        .query(
+            "IF $leaving != NONE {
+                UPDATE type::thing('stakers', $leaving.staker) SET
+                    recent_blocks -= 1,
+                    recent_share = <float> recent_blocks / $window,
+                    window = $window;
+            }",
+        )
+        .bind(("stake", stake.clone()))
+        .bind(("window", window))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+// The recent window can't be updated incrementally across a change of its size,
+// so stakers recorded with another window are recounted from their stakes.
+pub async fn regleaderboardwindow(
+    db: &Surreal<Client>,
+    nextheight: u64,
+    window: u64,
+) -> Result<(), Box<dyn Error>> {
+    trace!(
+        "Recounting stakers recorded with a leaderboard window other than {} ...",
+        window
+    );
+    db.query(
+        "FOR $staker IN (SELECT VALUE staker FROM stakers WHERE window != $window) {
+            LET $recent = count(SELECT height FROM stakes WHERE staker = $staker AND height >= $height - $window);
+            UPDATE type::thing('stakers', $staker) SET
+                recent_blocks = $recent,
+                recent_share = <float> $recent / $window,
+                window = $window;
+        }",
+    )
+    .bind(("height", nextheight))
+    .bind(("window", window))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+pub async fn getstakes(
+    db: &Surreal<Client>,
+    from_height: u64,
+    to_height: u64,
+) -> Result<Vec<Stake>, Box<dyn Error>> {
+    trace!(
+        "Querying stakes of blocks {}..{} ...",
+        from_height,
+        to_height
+    );
+    let mut response = db
+        .query("SELECT * FROM stakes WHERE height >= $from AND height < $to ORDER BY height")
+        .bind(("from", from_height))
+        .bind(("to", to_height))
+        .await?;
+    let stakes: Vec<Stake> = response.take(0)?;
+    Ok(stakes)
+}
+
+pub async fn regdecentralization(
+    db: &Surreal<Client>,
+    metrics: &[Metrics],
+) -> Result<(), Box<dyn Error>> {
+    trace!("Recording decentralization metrics ...");
+    db.query(
+        "FOR $metrics IN $all {
+            CREATE type::thing('decentralization', [$metrics.height, $metrics.window]) CONTENT $metrics;
+        }",
+    )
+    .bind(("all", metrics.to_vec()))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+// Per staker aggregate of $vote. Votes without a known staker only count in the tally.
+const VOTER: &str = "IF $vote.staker != NONE {
+    LET $voter = type::thing('voters', [$vote.proposal_id, $vote.staker]);
+    LET $previous = (SELECT VALUE option FROM $voter)[0];
+    LET $changed = $previous != NONE AND $previous != $vote.option;
+    IF $changed {
+        CREATE type::thing('votechanges', [$vote.proposal_id, $vote.staker, $vote.height]) CONTENT {
+            proposal_id: $vote.proposal_id,
+            staker: $vote.staker,
+            kind: $vote.kind,
+            height: $vote.height,
+            time: $vote.time,
+            previous_option: $previous,
+            option: $vote.option,
+        };
+    };
+    UPSERT $voter SET
+        proposal_id = $vote.proposal_id,
+        staker = $vote.staker,
+        kind = $vote.kind,
+        pool_url = $vote.pool_url,
+        option = $vote.option,
+        blocks += 1,
+        weight += $vote.weight ?? 0,
+        changes += IF $changed THEN 1 ELSE 0 END,
+        first_height = first_height ?? $vote.height,
+        last_height = $vote.height;
+}";
+
+pub async fn regvote(db: &Surreal<Client>, vote: &VoteRecord) -> Result<(), Box<dyn Error>> {
+    trace!(
+        "Recording vote for option {} of proposal {} ...",
+        vote.option,
+        vote.proposal_id
+    );
+    db.query("CREATE type::thing('votes', $vote.height) CONTENT $vote")
+        .query(VOTER)
+        .bind(("vote", vote.clone()))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn getvotedblocks(
+    db: &Surreal<Client>,
+    from: u64,
+    limit: u64,
+) -> Result<Vec<VotedBlock>, Box<dyn Error>> {
+    trace!("Querying voted blocks from height {} ...", from);
+    let mut response = db
+        .query(
+            "SELECT height, time, voting_info, coinstake, coldstaking, stakekernelvalue FROM blocks
+                WHERE voting_info != NONE AND height >= $from ORDER BY height LIMIT $limit",
+        )
+        .bind(("from", from))
+        .bind(("limit", limit))
+        .await?;
+    let blocks: Vec<VotedBlock> = response.take(0)?;
+    Ok(blocks)
+}
+
+// Overwrites votes already recorded, so the backfill can be rerun after an interruption.
+pub async fn regvotes(db: &Surreal<Client>, votes: &[VoteRecord]) -> Result<(), Box<dyn Error>> {
+    trace!("Recording {} votes ...", votes.len());
+    db.query(
+        "FOR $vote IN $votes {
+            UPSERT type::thing('votes', $vote.height) CONTENT $vote;
+        }",
+    )
+    .bind(("votes", votes.to_vec()))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+pub async fn delvoters(db: &Surreal<Client>) -> Result<(), Box<dyn Error>> {
+    db.query("DELETE voters; DELETE votechanges;")
+        .await?
+        .check()?;
+    Ok(())
+}
+
+// Replays up to limit recorded votes above the given height into the per staker aggregates.
+// Returns the height of the last one, unless there were none left.
+pub async fn regvoters(
+    db: &Surreal<Client>,
+    after: u64,
+    limit: u64,
+) -> Result<Option<u64>, Box<dyn Error>> {
+    trace!("Replaying votes above height {} ...", after);
+    let mut response = db
+        .query(
+            "LET $votes = SELECT * OMIT id FROM votes WHERE height > $after ORDER BY height LIMIT $limit",
+        )
+        .query(format!("FOR $vote IN $votes {{ {} }}", VOTER))
+        .query("RETURN math::max($votes.height)")
+        .bind(("after", after))
+        .bind(("limit", limit))
+        .await?
+        .check()?;
+    let height: Option<u64> = response.take(2)?;
+    Ok(height)
+}
+
+// Records the block and refreshes turnout over the trailing window and the day of the block.
+pub async fn regturnout(
+    db: &Surreal<Client>,
+    turnout: &BlockTurnout,
+    window: u64,
+) -> Result<(), Box<dyn Error>> {
+    trace!(
+        "Recording governance turnout of block {} ...",
+        turnout.height
+    );
+    db.query("CREATE type::thing('turnout', $turnout.height) CONTENT $turnout")
+        .query(
+            "LET $recent = SELECT staked, voted, proposal_id FROM turnout
+                WHERE height > $turnout.height - $window AND height <= $turnout.height",
+        )
+        .query(
+            "LET $staked = count($recent[WHERE staked = true]);
+            LET $voted = count($recent[WHERE voted = true]);
+            UPDATE type::thing('turnout', $turnout.height) SET
+                window_staked = $staked,
+                window_voted = $voted,
+                window = $window,
+                window_proposals = count(array::distinct($recent[WHERE proposal_id != NONE].proposal_id)),
+                window_turnout = IF $staked > 0 THEN <int> math::floor(<float> $voted * 10000 / $staked) ELSE 0 END",
+        )
+        .query(
+            "UPSERT type::thing('turnoutdaily', $turnout.day) SET
+                day = $turnout.day,
+                blocks += 1,
+                staked_blocks += IF $turnout.staked THEN 1 ELSE 0 END,
+                voted_blocks += IF $turnout.voted THEN 1 ELSE 0 END,
+                proposal_ids = array::union(proposal_ids ?? [], IF $turnout.proposal_id != NONE THEN [$turnout.proposal_id] ELSE [] END),
+                proposals = count(proposal_ids),
+                turnout = IF staked_blocks > 0 THEN <int> math::floor(<float> voted_blocks * 10000 / staked_blocks) ELSE 0 END",
+        )
+        .bind(("turnout", turnout.clone()))
+        .bind(("window", window))
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn delturnout(db: &Surreal<Client>) -> Result<(), Box<dyn Error>> {
+    db.query("DELETE turnout; DELETE turnoutdaily;")
+        .await?
+        .check()?;
+    Ok(())
+}
+
+pub async fn getrecordedblocks(
+    db: &Surreal<Client>,
+    from: u64,
+    limit: u64,
+) -> Result<Vec<RecordedBlock>, Box<dyn Error>> {
+    trace!("Querying recorded blocks from height {} ...", from);
+    let mut response = db
+        .query(
+            "SELECT height, time, hashproofofstake != NONE AS staked, voting_info.proposal_id AS proposal_id
+                FROM blocks WHERE height >= $from ORDER BY height LIMIT $limit",
+        )
+        .bind(("from", from))
+        .bind(("limit", limit))
+        .await?;
+    let blocks: Vec<RecordedBlock> = response.take(0)?;
+    Ok(blocks)
+}
+
+// Amounts of stored blocks from the given height on, with the height to tell them apart.
+pub async fn getblockamounts(
+    db: &Surreal<Client>,
+    from: u64,
+    limit: u64,
+) -> Result<Vec<Value>, Box<dyn Error>> {
+    trace!("Querying amounts of blocks from height {} ...", from);
+    let mut response = db
+        .query(
+            "SELECT height, stakekernelvalue, tx FROM blocks
+                WHERE height >= $from ORDER BY height LIMIT $limit",
+        )
+        .bind(("from", from))
+        .bind(("limit", limit))
+        .await?;
+    let blocks: Vec<Value> = response.take(0)?;
+    Ok(blocks)
+}
+
+pub async fn regblockamounts(db: &Surreal<Client>, blocks: &[Value]) -> Result<(), Box<dyn Error>> {
+    trace!("Rewriting amounts of {} blocks ...", blocks.len());
+    db.query(
+        "FOR $block IN $blocks {
+            UPDATE type::thing('blocks', $block.height) SET
+                stakekernelvalue = $block.stakekernelvalue,
+                tx = $block.tx;
+        }",
+    )
+    .bind(("blocks", blocks.to_vec()))
+    .await?
+    .check()?;
+    Ok(())
+}
+
+pub async fn getfirstvote(
+    db: &Surreal<Client>,
+    proposal_id: u64,
+) -> Result<Option<u64>, Box<dyn Error>> {
+    let mut response = db
+        .query("SELECT VALUE height FROM votes WHERE proposal_id = $proposal_id ORDER BY height LIMIT 1")
+        .bind(("proposal_id", proposal_id))
+        .await?;
+    let height: Option<u64> = response.take(0)?;
+    Ok(height)
+}
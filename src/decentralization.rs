@@ -0,0 +1,173 @@
+// Staking decentralization metrics over rolling windows of blocks.
+use crate::stakers::Stake;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Metrics {
+    pub height: u64,
+    pub time: u64,
+    // Window size in blocks.
+    pub window: u64,
+    // Staked blocks within the window.
+    pub blocks: u64,
+    pub stakers: u64,
+    // Smallest number of stakers controlling more than half of the blocks.
+    pub nakamoto: u64,
+    // Herfindahl–Hirschman index, from 0 to 10000.
+    pub hhi: f64,
+    pub top1_share: f64,
+    pub top5_share: f64,
+    pub top10_share: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Window {
+    size: u64,
+    stakes: VecDeque<(u64, String)>,
+    counts: HashMap<String, u64>,
+}
+
+impl Window {
+    fn push(&mut self, stake: &Stake) {
+        self.stakes.push_back((stake.height, stake.staker.clone()));
+        *self.counts.entry(stake.staker.clone()).or_default() += 1;
+        while let Some((height, staker)) = self.stakes.front() {
+            if height + self.size > stake.height {
+                break;
+            }
+            if let Some(count) = self.counts.get_mut(staker) {
+                *count -= 1;
+                if *count == 0 {
+                    self.counts.remove(staker);
+                }
+            }
+            self.stakes.pop_front();
+        }
+    }
+    fn metrics(&self, stake: &Stake) -> Metrics {
+        let blocks = self.stakes.len() as u64;
+        let mut counts: Vec<u64> = self.counts.values().copied().collect();
+        counts.sort_unstable_by(|a, b| b.cmp(a));
+        let share = |n: usize| counts.iter().take(n).sum::<u64>() as f64 / blocks as f64;
+        let mut nakamoto = 0;
+        let mut cumulative = 0;
+        for count in counts.iter() {
+            nakamoto += 1;
+            cumulative += count;
+            if cumulative * 2 > blocks {
+                break;
+            }
+        }
+        Metrics {
+            height: stake.height,
+            time: stake.time,
+            window: self.size,
+            blocks,
+            stakers: counts.len() as u64,
+            nakamoto,
+            hhi: counts
+                .iter()
+                .map(|count| (*count as f64 * 100.0 / blocks as f64).powi(2))
+                .sum(),
+            top1_share: share(1),
+            top5_share: share(5),
+            top10_share: share(10),
+        }
+    }
+}
+
+// Rolling windows kept in memory, so metrics don't need a scan over the stakes table per block.
+#[derive(Debug, Clone)]
+pub struct Windows(Vec<Window>);
+
+impl Windows {
+    pub fn new(sizes: &[u64]) -> Self {
+        Windows(
+            sizes
+                .iter()
+                .map(|size| Window {
+                    size: *size,
+                    stakes: VecDeque::new(),
+                    counts: HashMap::new(),
+                })
+                .collect(),
+        )
+    }
+    // Largest window, which determines how many recent stakes have to be loaded.
+    pub fn span(&self) -> u64 {
+        self.0.iter().map(|window| window.size).max().unwrap_or(0)
+    }
+    pub fn push(&mut self, stake: &Stake) -> Vec<Metrics> {
+        self.0
+            .iter_mut()
+            .map(|window| {
+                window.push(stake);
+                window.metrics(stake)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stakers::StakerKind;
+
+    fn stake(height: u64, staker: &str) -> Stake {
+        Stake {
+            height,
+            time: height * 120,
+            staker: staker.to_string(),
+            kind: StakerKind::Hot,
+            reward: 0,
+        }
+    }
+
+    #[test]
+    fn windows_evict_old_stakes_and_measure_concentration() {
+        let mut windows = Windows::new(&[2, 4]);
+        assert_eq!(windows.span(), 4);
+        // (height, staker, expected blocks, stakers, nakamoto, hhi, top1 share) of the 4-block window
+        let cases = [
+            (1, "a", 1, 1, 1, 10000.0, 1.0),
+            (2, "a", 2, 1, 1, 10000.0, 1.0),
+            (3, "b", 3, 2, 1, 5555.5, 2.0 / 3.0),
+            (4, "c", 4, 3, 2, 3750.0, 0.5),
+            (5, "a", 4, 3, 2, 3750.0, 0.5),
+            (6, "d", 4, 4, 3, 2500.0, 0.25),
+        ];
+        for (height, staker, blocks, stakers, nakamoto, hhi, top1) in cases {
+            let metrics = windows.push(&stake(height, staker));
+            assert_eq!(metrics.len(), 2);
+            let (short, long) = (&metrics[0], &metrics[1]);
+            assert_eq!(short.window, 2);
+            assert_eq!(short.blocks, height.min(2));
+            assert_eq!(
+                (long.height, long.time, long.window),
+                (height, height * 120, 4)
+            );
+            assert_eq!(long.blocks, blocks, "blocks at {}", height);
+            assert_eq!(long.stakers, stakers, "stakers at {}", height);
+            assert_eq!(long.nakamoto, nakamoto, "nakamoto at {}", height);
+            assert!(
+                (long.hhi - hhi).abs() < 0.1,
+                "hhi {} at {}",
+                long.hhi,
+                height
+            );
+            assert!((long.top1_share - top1).abs() < 1e-9);
+            assert_eq!(long.top5_share, 1.0);
+            assert_eq!(long.top10_share, 1.0);
+        }
+    }
+
+    #[test]
+    fn nakamoto_needs_a_strict_majority() {
+        let mut windows = Windows::new(&[2]);
+        windows.push(&stake(1, "a"));
+        let metrics = windows.push(&stake(2, "b"));
+        assert_eq!(metrics[0].nakamoto, 2);
+        assert_eq!(metrics[0].top1_share, 0.5);
+    }
+}
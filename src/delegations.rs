@@ -0,0 +1,123 @@
+// Cold-staking delegations of spending addresses to stake keys.
+use crate::{
+    pools::{self, Pool},
+    utxo::Utxo,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// Change of a single delegation within a block. Value is in satoshis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DelegationChange {
+    pub spend: String,
+    pub stakeonly: String,
+    pub pool: Pool,
+    pub height: u64,
+    // Cold-staking outputs created in this block.
+    pub outputs: u64,
+    pub delta: i64,
+}
+
+pub fn changes(created: &[Utxo], spent: &[Utxo], height: u64) -> Vec<DelegationChange> {
+    let mut changes: BTreeMap<(String, String), (u64, i64)> = BTreeMap::new();
+    for utxo in created.iter() {
+        if let (Some(coldstake), Some(valuesat)) = (&utxo.coldstake, utxo.valuesat) {
+            let change = changes
+                .entry((coldstake.spend.clone(), coldstake.stakeonly.clone()))
+                .or_default();
+            change.0 += 1;
+            change.1 += valuesat as i64;
+        }
+    }
+    for utxo in spent.iter() {
+        if let (Some(coldstake), Some(valuesat)) = (&utxo.coldstake, utxo.valuesat) {
+            changes
+                .entry((coldstake.spend.clone(), coldstake.stakeonly.clone()))
+                .or_default()
+                .1 -= valuesat as i64;
+        }
+    }
+    changes
+        .into_iter()
+        .map(|((spend, stakeonly), (outputs, delta))| DelegationChange {
+            pool: pools::identify(&stakeonly),
+            spend,
+            stakeonly,
+            height,
+            outputs,
+            delta,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{address::ColdstakeAddresses, pools::POOLS};
+
+    fn utxo(spend: &str, stakeonly: &str, valuesat: Option<u64>) -> Utxo {
+        Utxo {
+            outpoint: format!("{}:{}", spend, stakeonly),
+            txid: String::new(),
+            n: 0,
+            height: 0,
+            output_type: "standard".to_string(),
+            valuesat,
+            value_commitment: None,
+            addresses: None,
+            stakeaddresses: None,
+            template: None,
+            coldstake: Some(ColdstakeAddresses {
+                stakeonly: stakeonly.to_string(),
+                stake: String::new(),
+                spend: spend.to_string(),
+            }),
+            spent: None,
+        }
+    }
+
+    #[test]
+    fn changes_net_delegations_per_spend_and_stake_key() {
+        let pool = POOLS[0].pubkey;
+        let mut hot = utxo("owner", "unused", Some(1_000));
+        hot.coldstake = None;
+        let created = [
+            utxo("owner", pool, Some(700)),
+            utxo("owner", pool, Some(300)),
+            utxo("other", "pcs1solo", Some(50)),
+            utxo("blind", pool, None),
+            hot.clone(),
+        ];
+        let spent = [
+            utxo("owner", pool, Some(400)),
+            utxo("gone", "pcs1solo", Some(80)),
+            hot,
+        ];
+        let changes = changes(&created, &spent, 7);
+        let summary: Vec<(&str, &str, u64, i64)> = changes
+            .iter()
+            .map(|change| {
+                (
+                    change.spend.as_str(),
+                    change.stakeonly.as_str(),
+                    change.outputs,
+                    change.delta,
+                )
+            })
+            .collect();
+        // Undelegating everything leaves a change without any new outputs.
+        assert_eq!(
+            summary,
+            [
+                ("gone", "pcs1solo", 0, -80),
+                ("other", "pcs1solo", 1, 50),
+                ("owner", pool, 2, 600),
+            ]
+        );
+        assert!(changes.iter().all(|change| change.height == 7));
+        // Pools are attributed by their stake-only address.
+        assert_eq!(changes[2].pool.url.as_deref(), Some(POOLS[0].url));
+        assert_eq!(changes[1].pool.pubkey, "pcs1solo");
+        assert_eq!(changes[1].pool.url, None);
+    }
+}
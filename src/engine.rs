@@ -1,4 +1,16 @@
-use crate::{console::*, db, rpc::RPCURL};
+use crate::{
+    amount::Amount,
+    args::Settings,
+    balances,
+    console::*,
+    db,
+    decentralization::Windows,
+    delegations, fees, migrations, privacy, registry, rewards, rings,
+    rpc::RPCURL,
+    smsg, stakers,
+    tally::{self, TALLY_START},
+    treasury, turnout, utxo, votes,
+};
 use bitcoincore_zmq::{subscribe_async, Message, Message::HashBlock};
 use clap::ArgMatches;
 use futures_util::StreamExt;
@@ -7,7 +19,7 @@ use serde::{Deserialize, Serialize};
 use std::error::Error;
 use surrealdb::{engine::remote::ws::Client, Surreal};
 
-pub async fn run(args: &ArgMatches) {
+fn rpcurl(args: &ArgMatches) -> RPCURL {
     let ipsplit: Vec<&str> = args
         .get_one::<String>("Particld IP")
         .unwrap()
@@ -17,53 +29,344 @@ pub async fn run(args: &ArgMatches) {
         error!("Particld IP parsing error.");
         std::process::exit(1);
     }
-    let rpcurl = RPCURL::default().target(
+    RPCURL::default().target(
         ipsplit[0],
         ipsplit[1].parse::<u16>().unwrap(),
         "",
-        &args.get_one::<String>("user").unwrap(),
-        &args.get_one::<String>("password").unwrap(),
-    );
+        args.get_one::<String>("user").unwrap(),
+        args.get_one::<String>("password").unwrap(),
+    )
+}
+
+pub async fn run(args: &ArgMatches) {
+    let rpcurl = rpcurl(args);
+    let settings = Settings::from_args(args);
     let db = db::init(args).await;
-    if let Err(e) = catchup(&db, &rpcurl).await {
+    if let Err(e) = migrations::migrate(&db, &rpcurl, &settings, false).await {
+        error!("Failed to migrate the database: {}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = catchup(&db, &rpcurl, &settings).await {
         error!("{}", e);
         std::process::exit(1);
     }
-    if let Err(e) = listen(&db, &rpcurl).await {
+    if let Err(e) = listen(&db, &rpcurl, &settings).await {
         error!("{}", e);
         std::process::exit(1);
     }
 }
 
+// Prints the tally of a proposal over a range of recorded blocks, without asking particld.
+pub async fn tally(args: &ArgMatches, tally: &ArgMatches) {
+    let db = db::init(args).await;
+    let proposal_id = *tally.get_one::<u64>("proposal").unwrap();
+    let from_height = *tally.get_one::<u64>("from").unwrap();
+    let height = match tally.get_one::<u64>("height") {
+        Some(height) => Ok(Some(*height)),
+        None => db::toprec(&db).await,
+    };
+    let votes = match height {
+        Ok(Some(height)) => db::getoptionvotes(&db, proposal_id, from_height, height)
+            .await
+            .map(|votes| (height, votes)),
+        Ok(None) => {
+            error!("No blocks are recorded yet.");
+            std::process::exit(1);
+        }
+        Err(e) => Err(e),
+    };
+    match votes {
+        Ok((height, votes)) => {
+            let mut proposal = tally::tally(proposal_id, from_height, height, &votes);
+            registry::label(&mut proposal, &Settings::from_args(args).registry);
+            println!("{}", serde_json::to_string_pretty(&proposal).unwrap());
+        }
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+pub async fn migrate(args: &ArgMatches, dry_run: bool) {
+    let rpcurl = rpcurl(args);
+    let settings = Settings::from_args(args);
+    let db = db::init(args).await;
+    if let Err(e) = migrations::migrate(&db, &rpcurl, &settings, dry_run).await {
+        error!("Failed to migrate the database: {}", e);
+        std::process::exit(1);
+    }
+}
+
 async fn scan(
     blockhash: &String,
     proposal_ids: &mut Vec<u64>,
+    windows: &mut Windows,
     db: &Surreal<Client>,
     rpcurl: &RPCURL,
+    settings: &Settings,
 ) -> Result<(), Box<dyn Error>> {
-    let blockdata: BlockData = getblock(blockhash, db, &rpcurl).await?;
-    if let Ok(Some(proposal)) = getnewproposal(&blockdata, &proposal_ids, &rpcurl).await {
-        db::regproposal(&db, &proposal).await?;
-        *proposal_ids = db::getproposalids(&db).await?;
+    let mut blockdata: BlockData = getblock(blockhash, db, rpcurl).await?;
+    if db::getblockhash(db, blockdata.height).await?.as_ref() == Some(blockhash) {
+        trace!("Block {} is already recorded.", blockdata.height);
+        return Ok(());
+    }
+    let forkheight = forkpoint(&blockdata, db, rpcurl).await?;
+    if db::getblockhash(db, forkheight).await?.is_some() {
+        warn!(
+            "Reorganization detected. Rolling back blocks from height {}.",
+            forkheight
+        );
+        db::rollback(db, forkheight, settings).await?;
+        *windows = loadwindows(forkheight, db, settings).await?;
+        *proposal_ids = db::getproposalids(db).await?;
     }
-    db::regblock(&db, &blockdata).await?;
+    // Blocks between the fork and the announced one have to be replayed from the new chain.
+    for height in forkheight..blockdata.height {
+        let mut replayed = getblock(getblockhash(height, rpcurl).await?, db, rpcurl).await?;
+        index(&mut replayed, proposal_ids, windows, db, rpcurl, settings).await?;
+    }
+    index(&mut blockdata, proposal_ids, windows, db, rpcurl, settings).await?;
     Ok(())
 }
 
-async fn catchup(db: &Surreal<Client>, rpcurl: &RPCURL) -> Result<(), Box<dyn Error>> {
+// Walks back from the given block until the recorded chain agrees with the node.
+// Returns the lowest height which has to be (re)indexed, which is at most the one above
+// the top recorded block, so a block announced ahead of the recorded chain doesn't leave a gap.
+async fn forkpoint(
+    blockdata: &BlockData,
+    db: &Surreal<Client>,
+    rpcurl: &RPCURL,
+) -> Result<u64, Box<dyn Error>> {
+    let mut height = blockdata.height;
+    let mut parenthash = blockdata.previousblockhash.clone();
+    while height > 0 {
+        match db::getblockhash(db, height - 1).await? {
+            Some(recorded) if Some(&recorded) == parenthash.as_ref() => break,
+            // Either the parent was reorganized away or it isn't recorded yet.
+            _ => {
+                height -= 1;
+                parenthash = match height {
+                    0 => None,
+                    _ => Some(getblockhash(height - 1, rpcurl).await?),
+                };
+            }
+        }
+    }
+    Ok(height)
+}
+
+async fn index(
+    blockdata: &mut BlockData,
+    proposal_ids: &mut Vec<u64>,
+    windows: &mut Windows,
+    db: &Surreal<Client>,
+    rpcurl: &RPCURL,
+    settings: &Settings,
+) -> Result<(), Box<dyn Error>> {
+    let shapes = blockdata.unknown_shapes();
+    if !shapes.is_empty() {
+        let total = db::regunknownshapes(db, &shapes).await?;
+        warn!(
+            "Block {} has {} inputs, outputs or votes of unknown shape, {} seen so far.",
+            blockdata.height,
+            shapes.len(),
+            total
+        );
+    }
+    let created = utxo::created(blockdata);
+    db::regutxos(db, &created).await?;
+    let spent = db::spendutxos(db, &utxo::spends(blockdata)).await?;
+    let deltas = balances::deltas(&created, &spent);
+    let current = db::getbalances(db, deltas.keys().cloned().collect()).await?;
+    db::regbalances(db, &balances::apply(&current, &deltas, blockdata.height)).await?;
+    db::regdelegations(
+        db,
+        &delegations::changes(&created, &spent, blockdata.height),
+    )
+    .await?;
+    let txfees = fees::fees(blockdata, &spent);
+    db::regfees(db, &txfees, &fees::summarize(blockdata, &txfees)).await?;
+    blockdata.coinstake = rewards::decompose(blockdata, &spent);
+    if let Some(coinstake) = &blockdata.coinstake {
+        trace!("Coinstake reward: {} PART.", Amount(coinstake.reward));
+    }
+    if let Some(stake) = stakers::stake(blockdata) {
+        db::regstake(db, &stake, settings.leaderboard_window).await?;
+        db::regdecentralization(db, &windows.push(&stake)).await?;
+    }
+    if let Some(vote) = votes::vote(blockdata) {
+        db::regvote(db, &vote).await?;
+        // Proposal gets registered with its first vote.
+        if !proposal_ids.contains(&vote.proposal_id) {
+            regtally(
+                vote.proposal_id,
+                blockdata.height,
+                false,
+                db,
+                rpcurl,
+                settings,
+            )
+            .await?;
+            *proposal_ids = db::getproposalids(db).await?;
+        }
+    }
+    if blockdata.height % settings.retally_interval == 0 {
+        retally(blockdata.height, db, rpcurl, settings).await?;
+    }
+    db::regturnout(db, &turnout::block(blockdata), settings.turnout_window).await?;
+    let previous = match blockdata.height {
+        0 => None,
+        height => db::gettreasury(db, height - 1).await?,
+    };
+    let addresses = treasury::TREASURY_ADDRESSES.map(String::from).to_vec();
+    let balance = db::getbalances(db, addresses)
+        .await?
+        .iter()
+        .map(|b| b.balance)
+        .sum();
+    db::regtreasury(db, &treasury::entry(blockdata, previous.as_ref(), balance)).await?;
+    let previous = match blockdata.height {
+        0 => None,
+        height => db::getsmsg(db, height - 1).await?,
+    };
+    if let Some(params) = smsg::params(blockdata, previous.as_ref()) {
+        db::regsmsg(db, &params).await?;
+    }
+    db::regprivacy(db, &privacy::stats(blockdata, &spent)).await?;
+    let ringinputs = rings::inputs(blockdata);
+    db::regrings(db, &ringinputs, &rings::stats(blockdata, &ringinputs)).await?;
+    // Block goes last, so a recorded block guarantees that everything derived from it is recorded too.
+    db::regblock(db, blockdata, settings.storage_profile).await?;
+    Ok(())
+}
+
+// Re-tallies the proposals which received votes since the previous re-tally
+// and updates the lifecycle of the proposals in the registry.
+// Every so often the result is cross-checked against tallyvotes of particld.
+async fn retally(
+    height: u64,
+    db: &Surreal<Client>,
+    rpcurl: &RPCURL,
+    settings: &Settings,
+) -> Result<(), Box<dyn Error>> {
+    let check = settings.tally_check_every != 0
+        && (height / settings.retally_interval) % settings.tally_check_every == 0;
+    let from_height = (height + 1).saturating_sub(settings.retally_interval);
+    for proposal_id in db::getvotedproposals(db, from_height, height).await? {
+        regtally(proposal_id, height, check, db, rpcurl, settings).await?;
+    }
+    for meta in settings.registry.iter() {
+        let votes = match registry::closed_period(meta, height) {
+            Some((start, end)) => db::getoptionvotes(db, meta.proposal_id, start, end).await?,
+            None => Vec::new(),
+        };
+        let state = registry::lifecycle(meta, height, &votes);
+        db::regproposalstate(db, meta, state, height).await?;
+    }
+    Ok(())
+}
+
+async fn regtally(
+    proposal_id: u64,
+    height: u64,
+    check: bool,
+    db: &Surreal<Client>,
+    rpcurl: &RPCURL,
+    settings: &Settings,
+) -> Result<(), Box<dyn Error>> {
+    let votes = db::getoptionvotes(db, proposal_id, TALLY_START, height).await?;
+    let mut proposal = tally::tally(proposal_id, TALLY_START, height, &votes);
+    registry::label(&mut proposal, &settings.registry);
+    let first_vote = db::getfirstvote(db, proposal_id).await?.unwrap_or(height);
+    let meta = settings
+        .registry
+        .iter()
+        .find(|meta| meta.proposal_id == proposal_id);
+    let (start, end) = turnout::window(meta, first_vote, height);
+    let voted = db::getoptionvotes(db, proposal_id, start, end)
+        .await?
+        .iter()
+        .map(|option| option.votes)
+        .sum();
+    proposal.participation = Some(turnout::participation(start, end, voted));
+    if check {
+        let mut node = tallyvotes(proposal_id, height, rpcurl).await?;
+        let mismatches = tally::mismatches(&proposal, &node);
+        if mismatches.is_empty() {
+            trace!(
+                "Tally of proposal {} at height {} matches tallyvotes.",
+                proposal_id,
+                height
+            );
+        } else {
+            // Recorded votes are incomplete or misread, so the native weights can't be trusted either.
+            warn!(
+                "Tally of proposal {} at height {} differs from tallyvotes: {}. Keeping the stats of the node.",
+                proposal_id,
+                height,
+                mismatches.join("; ")
+            );
+            registry::label(&mut node, &settings.registry);
+            node.participation = proposal.participation.take();
+            return db::regproposal(db, &node).await;
+        }
+    }
+    db::regproposal(db, &proposal).await
+}
+
+// Refills rolling windows with the stakes recorded below the given height.
+async fn loadwindows(
+    height: u64,
+    db: &Surreal<Client>,
+    settings: &Settings,
+) -> Result<Windows, Box<dyn Error>> {
+    let mut windows = Windows::new(&settings.decentralization_windows);
+    let from_height = height.saturating_sub(windows.span());
+    for stake in db::getstakes(db, from_height, height).await? {
+        windows.push(&stake);
+    }
+    Ok(windows)
+}
+
+async fn catchup(
+    db: &Surreal<Client>,
+    rpcurl: &RPCURL,
+    settings: &Settings,
+) -> Result<(), Box<dyn Error>> {
     info!("Catching up the blocks ...");
-    let nextheight = match db::toprec(&db).await? {
+    let nextheight = match db::toprec(db).await? {
         // Continue building database from last recorded block + 1.
         Some(thing) => thing + 1,
         // This is a start height from which database is going to be initialized.
         None => 0,
     };
-    let mut proposal_ids = db::getproposalids(&db).await?;
+    // Every staked block creates outputs, so recorded blocks without any recorded output
+    // were indexed before outputs were tracked. Spends of their outputs can't be resolved,
+    // which would silently skew balances, fees and rewards from here on.
+    if nextheight > 0 && db::getanyutxo(db).await?.is_none() {
+        return Err("Blocks were indexed before unspent outputs were tracked. \
+            Reindex the chain into an empty database."
+            .into());
+    }
+    // Discard leftovers of a block which was interrupted halfway through indexing.
+    db::rollback(db, nextheight, settings).await?;
+    db::regleaderboardwindow(db, nextheight, settings.leaderboard_window).await?;
+    let mut proposal_ids = db::getproposalids(db).await?;
+    let mut windows = loadwindows(nextheight, db, settings).await?;
     for height in nextheight.. {
         let blockhash_result = getblockhash(height, rpcurl).await;
         match blockhash_result {
             Ok(blockhash) => {
-                scan(&blockhash, &mut proposal_ids, &db, &rpcurl).await?;
+                scan(
+                    &blockhash,
+                    &mut proposal_ids,
+                    &mut windows,
+                    db,
+                    rpcurl,
+                    settings,
+                )
+                .await?;
             }
             Err(e) => {
                 error!("{}", e);
@@ -92,19 +395,33 @@ impl ProcessedBlocks {
     }
 }
 
-async fn listen(db: &Surreal<Client>, rpcurl: &RPCURL) -> Result<(), Box<dyn Error>> {
-    let mut proposal_ids = db::getproposalids(&db).await?;
+async fn listen(
+    db: &Surreal<Client>,
+    rpcurl: &RPCURL,
+    settings: &Settings,
+) -> Result<(), Box<dyn Error>> {
+    let mut proposal_ids = db::getproposalids(db).await?;
+    let nextheight = db::toprec(db).await?.map_or(0, |height| height + 1);
+    let mut windows = loadwindows(nextheight, db, settings).await?;
     let mut processed_blocks = ProcessedBlocks::default();
-    if let Some(blocks) = db::gettrackedzmq(&db).await? {
+    if let Some(blocks) = db::gettrackedzmq(db).await? {
         processed_blocks = blocks;
     }
     let mut stream = subscribe_async(&["tcp://particld:28332"])?;
     while let Some(msg) = stream.next().await {
         let blockhash = gethash(msg);
         if !processed_blocks.contains(&blockhash) {
-            scan(&blockhash, &mut proposal_ids, &db, rpcurl).await?;
+            scan(
+                &blockhash,
+                &mut proposal_ids,
+                &mut windows,
+                db,
+                rpcurl,
+                settings,
+            )
+            .await?;
             processed_blocks.inject(blockhash);
-            db::regtrackedzmq(&db, &processed_blocks).await?;
+            db::regtrackedzmq(db, &processed_blocks).await?;
         }
     }
     Ok(())
@@ -113,9 +430,7 @@ async fn listen(db: &Surreal<Client>, rpcurl: &RPCURL) -> Result<(), Box<dyn Err
 fn gethash<E: Error + Sized>(msg: Result<Message, E>) -> String {
     match msg {
         Ok(msg) => match msg {
-            HashBlock(hash, _) => {
-                return hash.to_string();
-            }
+            HashBlock(hash, _) => hash.to_string(),
             _ => {
                 error!("Got unexpected value from ZMQ.");
                 std::process::exit(1);
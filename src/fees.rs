@@ -0,0 +1,280 @@
+// Transaction fees computed from resolved public inputs.
+use crate::{
+    console::{BlockData, Transaction, Vin, Vout},
+    utxo::{outpoint, Utxo},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Reason why the fee of a transaction can't be derived from public amounts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unknowable {
+    BlindInputs,
+    AnonInputs,
+    BlindOutputs,
+    UnresolvedInputs,
+    UnknownShapes,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fee {
+    pub txid: String,
+    pub height: u64,
+    pub vsize: u64,
+    // Satoshis.
+    pub fee: Option<u64>,
+    // Satoshis per virtual byte.
+    pub feerate: Option<f64>,
+    pub unknowable: Option<Unknowable>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockFees {
+    pub height: u64,
+    pub time: u64,
+    pub txs: u64,
+    pub known: u64,
+    pub unknowable: u64,
+    pub total: u64,
+    pub min_feerate: Option<f64>,
+    pub median_feerate: Option<f64>,
+    pub max_feerate: Option<f64>,
+}
+
+fn fee(tx: &Transaction, height: u64, spent: &HashMap<String, &Utxo>) -> Fee {
+    let mut fee = Fee {
+        txid: tx.txid.clone(),
+        height,
+        vsize: tx.vsize,
+        fee: None,
+        feerate: None,
+        unknowable: None,
+    };
+    // Confidential transactions can't be balanced from public amounts, but carry their fee in a data output.
+    let ct_fee = tx.vout.iter().find_map(|vout| match vout {
+        Vout::Data {
+            ct_fee: Some(ct_fee),
+            ..
+        } => Some(ct_fee.sat()),
+        _ => None,
+    });
+    if let Some(ct_fee) = ct_fee {
+        fee.fee = Some(ct_fee);
+        fee.feerate = Some(ct_fee as f64 / tx.vsize as f64);
+        return fee;
+    }
+    let mut inputs: u64 = 0;
+    for vin in tx.vin.iter() {
+        let valuesat = match vin {
+            Vin::Standard { txid, vout, .. } => match spent.get(&outpoint(txid, *vout)) {
+                Some(utxo) => utxo.valuesat,
+                None => {
+                    fee.unknowable = Some(Unknowable::UnresolvedInputs);
+                    return fee;
+                }
+            },
+            Vin::Anon { .. } => {
+                fee.unknowable = Some(Unknowable::AnonInputs);
+                return fee;
+            }
+            Vin::Coinbase { .. } | Vin::Unknown(_) => {
+                fee.unknowable = Some(Unknowable::UnknownShapes);
+                return fee;
+            }
+        };
+        match valuesat {
+            Some(valuesat) => inputs += valuesat,
+            None => {
+                fee.unknowable = Some(Unknowable::BlindInputs);
+                return fee;
+            }
+        }
+    }
+    let mut outputs: u64 = 0;
+    for vout in tx.vout.iter() {
+        match vout {
+            Vout::Standard { valuesat, .. } => outputs += valuesat,
+            Vout::Data { .. } => {}
+            Vout::Blind { .. } | Vout::Anon { .. } => {
+                fee.unknowable = Some(Unknowable::BlindOutputs);
+                return fee;
+            }
+            Vout::Unknown(_) => {
+                fee.unknowable = Some(Unknowable::UnknownShapes);
+                return fee;
+            }
+        }
+    }
+    if outputs > inputs {
+        error!(
+            "Transaction {} spends more than it has on its inputs. Database is insane!",
+            tx.txid
+        );
+        std::process::exit(1);
+    }
+    fee.fee = Some(inputs - outputs);
+    fee.feerate = Some((inputs - outputs) as f64 / tx.vsize as f64);
+    fee
+}
+
+// Fees of every transaction except the coinbase or coinstake one, which is always first.
+pub fn fees(blockdata: &BlockData, spent: &[Utxo]) -> Vec<Fee> {
+    let spent: HashMap<String, &Utxo> = spent
+        .iter()
+        .map(|utxo| (utxo.outpoint.clone(), utxo))
+        .collect();
+    blockdata
+        .tx
+        .iter()
+        .skip(1)
+        .map(|tx| fee(tx, blockdata.height, &spent))
+        .collect()
+}
+
+pub fn summarize(blockdata: &BlockData, fees: &[Fee]) -> BlockFees {
+    let mut feerates: Vec<f64> = fees.iter().filter_map(|fee| fee.feerate).collect();
+    feerates.sort_by(|a, b| a.total_cmp(b));
+    BlockFees {
+        height: blockdata.height,
+        time: blockdata.time,
+        txs: fees.len() as u64,
+        known: feerates.len() as u64,
+        unknowable: fees.iter().filter(|fee| fee.unknowable.is_some()).count() as u64,
+        total: fees.iter().filter_map(|fee| fee.fee).sum(),
+        min_feerate: feerates.first().copied(),
+        median_feerate: feerates.get(feerates.len() / 2).copied(),
+        max_feerate: feerates.last().copied(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::tx_coins_to_sats;
+    use serde_json::{json, Value};
+
+    fn tx(vin: Value, vout: Value) -> Transaction {
+        let mut tx = json!({
+            "txid": "spender",
+            "hash": "spender",
+            "version": 160,
+            "size": 200,
+            "vsize": 200,
+            "weight": 800,
+            "locktime": 0,
+            "hex": "",
+            "vin": vin,
+            "vout": vout,
+        });
+        tx_coins_to_sats(&mut tx);
+        serde_json::from_value(tx).unwrap()
+    }
+
+    fn standard_vin(txid: &str) -> Value {
+        json!({"txid": txid, "vout": 0, "scriptSig": {"asm": "", "hex": ""}})
+    }
+
+    fn standard_vout(valuesat: u64) -> Value {
+        json!({
+            "n": 0,
+            "type": "standard",
+            "value": valuesat as f64 / 1e8,
+            "valueSat": valuesat,
+            "scriptPubKey": {"asm": "", "hex": "", "type": "pubkeyhash"},
+        })
+    }
+
+    fn blind_vout() -> Value {
+        json!({
+            "n": 1,
+            "type": "blind",
+            "valueCommitment": "08",
+            "data_hex": "",
+            "rangeproof": "",
+        })
+    }
+
+    fn utxo(txid: &str, valuesat: Option<u64>) -> Utxo {
+        Utxo {
+            outpoint: outpoint(txid, 0),
+            txid: txid.to_string(),
+            n: 0,
+            height: 0,
+            output_type: "standard".to_string(),
+            valuesat,
+            value_commitment: None,
+            addresses: None,
+            stakeaddresses: None,
+            template: None,
+            coldstake: None,
+            spent: None,
+        }
+    }
+
+    #[test]
+    fn fee_cases() {
+        let public = utxo("public", Some(10_000));
+        let blind = utxo("blind", None);
+        let spent: HashMap<String, &Utxo> = [&public, &blind]
+            .into_iter()
+            .map(|utxo| (utxo.outpoint.clone(), utxo))
+            .collect();
+        let anon_vin = json!({
+            "type": "anon",
+            "num_inputs": 1,
+            "ring_size": 3,
+            "txinwitness": [],
+            "sequence": 4294967295u64,
+        });
+        let ct_fee = json!({"n": 0, "type": "data", "data_hex": "", "ct_fee": 0.00004});
+        // (transaction, expected fee, expected reason)
+        let cases = [
+            (
+                tx(
+                    json!([standard_vin("public")]),
+                    json!([standard_vout(9_000)]),
+                ),
+                Some(1_000),
+                None,
+            ),
+            (
+                tx(
+                    json!([standard_vin("missing")]),
+                    json!([standard_vout(9_000)]),
+                ),
+                None,
+                Some(Unknowable::UnresolvedInputs),
+            ),
+            (
+                tx(
+                    json!([standard_vin("blind")]),
+                    json!([standard_vout(9_000)]),
+                ),
+                None,
+                Some(Unknowable::BlindInputs),
+            ),
+            (
+                tx(json!([anon_vin]), json!([standard_vout(9_000)])),
+                None,
+                Some(Unknowable::AnonInputs),
+            ),
+            (
+                tx(json!([standard_vin("public")]), json!([blind_vout()])),
+                None,
+                Some(Unknowable::BlindOutputs),
+            ),
+            (
+                tx(json!([anon_vin]), json!([ct_fee, blind_vout()])),
+                Some(4_000),
+                None,
+            ),
+        ];
+        for (tx, expected_fee, expected_reason) in cases {
+            let fee = fee(&tx, 1, &spent);
+            assert_eq!(fee.fee, expected_fee);
+            assert_eq!(fee.unknowable, expected_reason);
+            assert_eq!(fee.feerate, expected_fee.map(|fee| fee as f64 / 200.0));
+        }
+    }
+}
@@ -1,5 +1,4 @@
 use crate::CRATE_NAME;
-use fern;
 use fern::colors::{Color, ColoredLevelConfig};
 use humantime::format_rfc3339_seconds as timestamp;
 use std::time::SystemTime;
@@ -2,17 +2,42 @@
 #[macro_use]
 extern crate log;
 pub const CRATE_NAME: &str = module_path!();
+mod address;
+mod amount;
 mod args;
+mod balances;
 mod console;
 mod db;
+mod decentralization;
+mod delegations;
 mod engine;
+mod fees;
 mod logger;
+mod migrations;
 mod pools;
+mod privacy;
+mod registry;
+mod rewards;
+mod rings;
 mod rpc;
+mod schema;
+mod script;
+mod smsg;
+mod stakers;
+mod storage;
+mod tally;
+mod treasury;
+mod turnout;
+mod utxo;
+mod votes;
 
 #[tokio::main]
 async fn main() {
     let args = args::args();
     logger::init();
-    engine::run(&args).await;
+    match args.subcommand() {
+        Some(("migrate", migrate)) => engine::migrate(&args, migrate.get_flag("dry run")).await,
+        Some(("tally", tally)) => engine::tally(&args, tally).await,
+        _ => engine::run(&args).await,
+    }
 }
@@ -0,0 +1,394 @@
+// Ordered upgrades of the stored data, tracked by the schema_version record.
+use crate::{
+    args::Settings,
+    console::{block_coins_to_sats, parse_tallyvotes_ratios, Stakeaddress},
+    db, pools, rings,
+    rpc::{call, RPCURL},
+    schema::{
+        BLOCK_AMOUNTS, OPTION_STATS, PROPOSAL_STATES, RING_KEY_IMAGES, SCHEMA, TALLIES, TURNOUT,
+        UNKNOWN_SHAPES, VOTERS, VOTES,
+    },
+    tally, turnout,
+    votes::{self, VoteRecord},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{collections::HashMap, error::Error};
+use surrealdb::{engine::remote::ws::Client, Surreal};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SchemaVersion {
+    pub version: u64,
+}
+
+pub enum Step {
+    // Runs as a single transaction together with the version bump.
+    Query(&'static str),
+    // Recomputes stored data in Rust, possibly through RPC.
+    Retally,
+    // Converts stats maps of proposals and tallies into typed options.
+    TypeStats,
+    // Decodes key images of recorded anon inputs from their raw transactions.
+    KeyImages,
+    // Records stakeaddresses of cold-staking outputs decoded without RPC.
+    StakeAddresses,
+    // Records votes of blocks indexed before votes were tracked.
+    Votes,
+    // Rebuilds per staker vote aggregates by replaying the recorded votes.
+    Voters,
+    // Rebuilds governance turnout by replaying the recorded blocks.
+    Turnout,
+    // Converts amounts of blocks stored in coins into satoshis.
+    BlockAmounts,
+}
+
+pub struct Migration {
+    pub version: u64,
+    pub description: &'static str,
+    pub step: Step,
+}
+
+// Append only. Applied migrations must never be edited or reordered.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Define tables, typed fields and indexes",
+        step: Step::Query(SCHEMA),
+    },
+    Migration {
+        version: 2,
+        description: "Re-tally proposal stats into basis points",
+        step: Step::Retally,
+    },
+    Migration {
+        version: 3,
+        description: "Define the votes table",
+        step: Step::Query(VOTES),
+    },
+    Migration {
+        version: 4,
+        description: "Convert amounts of stored blocks from coins into satoshis",
+        step: Step::BlockAmounts,
+    },
+    Migration {
+        version: 5,
+        description: "Type amounts of blocks as integer satoshis",
+        step: Step::Query(BLOCK_AMOUNTS),
+    },
+    Migration {
+        version: 6,
+        description: "Record votes of blocks indexed before votes were tracked",
+        step: Step::Votes,
+    },
+    Migration {
+        version: 7,
+        description: "Define the tallies table",
+        step: Step::Query(TALLIES),
+    },
+    Migration {
+        version: 8,
+        description: "Define proposal metadata and lifecycle state",
+        step: Step::Query(PROPOSAL_STATES),
+    },
+    Migration {
+        version: 9,
+        description: "Define typed option stats of proposals and tallies",
+        step: Step::Query(OPTION_STATS),
+    },
+    Migration {
+        version: 10,
+        description: "Convert recorded stats into typed option stats",
+        step: Step::TypeStats,
+    },
+    Migration {
+        version: 11,
+        description: "Define the voters and votechanges tables",
+        step: Step::Query(VOTERS),
+    },
+    Migration {
+        version: 12,
+        description: "Define the turnout tables and proposal participation",
+        step: Step::Query(TURNOUT),
+    },
+    Migration {
+        version: 13,
+        description: "Define and index key images of anon inputs",
+        step: Step::Query(RING_KEY_IMAGES),
+    },
+    Migration {
+        version: 14,
+        description: "Decode key images of recorded anon inputs",
+        step: Step::KeyImages,
+    },
+    Migration {
+        version: 15,
+        description: "Accept malformed votes as unknown shapes and count them incrementally",
+        step: Step::Query(UNKNOWN_SHAPES),
+    },
+    Migration {
+        version: 16,
+        description: "Record stakeaddresses of natively decoded cold-staking outputs",
+        step: Step::StakeAddresses,
+    },
+    Migration {
+        version: 17,
+        description: "Aggregate votes recorded before voters were tracked",
+        step: Step::Voters,
+    },
+    Migration {
+        version: 18,
+        description: "Record turnout of blocks indexed before turnout was tracked",
+        step: Step::Turnout,
+    },
+];
+
+// Applies every migration above the recorded version. With dry_run only lists them.
+pub async fn migrate(
+    db: &Surreal<Client>,
+    rpcurl: &RPCURL,
+    settings: &Settings,
+    dry_run: bool,
+) -> Result<(), Box<dyn Error>> {
+    let version = db::getschemaversion(db).await?;
+    let pending: Vec<&Migration> = MIGRATIONS.iter().filter(|m| m.version > version).collect();
+    if pending.is_empty() {
+        info!("Schema version {} is up to date.", version);
+        return Ok(());
+    }
+    for migration in pending {
+        if dry_run {
+            info!(
+                "Would apply migration {}: {}.",
+                migration.version, migration.description
+            );
+            continue;
+        }
+        info!(
+            "Applying migration {}: {} ...",
+            migration.version, migration.description
+        );
+        match migration.step {
+            Step::Query(query) => db::regquerymigration(db, migration, query).await?,
+            Step::Retally => {
+                retally(db, rpcurl).await?;
+                db::regschemaversion(db, migration).await?;
+            }
+            Step::TypeStats => {
+                typestats(db).await?;
+                db::regschemaversion(db, migration).await?;
+            }
+            Step::KeyImages => {
+                keyimages(db, rpcurl).await?;
+                db::regschemaversion(db, migration).await?;
+            }
+            Step::StakeAddresses => {
+                stakeaddresses(db).await?;
+                db::regschemaversion(db, migration).await?;
+            }
+            Step::Votes => {
+                votes(db).await?;
+                db::regschemaversion(db, migration).await?;
+            }
+            Step::Voters => {
+                voters(db).await?;
+                db::regschemaversion(db, migration).await?;
+            }
+            Step::Turnout => {
+                turnouts(db, settings.turnout_window).await?;
+                db::regschemaversion(db, migration).await?;
+            }
+            Step::BlockAmounts => {
+                blockamounts(db).await?;
+                db::regschemaversion(db, migration).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+// Stats of stages created before percentages were stored as basis points are floats.
+// The conversion is spelled out here, so later changes to tallying leave this migration as it shipped.
+async fn retally(db: &Surreal<Client>, rpcurl: &RPCURL) -> Result<(), Box<dyn Error>> {
+    for proposal_id in db::getproposalids(db).await? {
+        let arg = format!("tallyvotes {} 616958 {}", proposal_id, i32::MAX);
+        let rawmap: HashMap<String, Value> = serde_json::from_value(call(&arg, rpcurl)?)?;
+        let stats: HashMap<String, (u64, u64)> = rawmap
+            .into_iter()
+            .filter(|(key, _)| {
+                !["proposal", "blocks_counted", "height_start", "height_end"]
+                    .contains(&key.as_str())
+            })
+            .map(|(key, value)| {
+                let ratios = parse_tallyvotes_ratios(&value.to_string().replace('"', ""))?;
+                Some((key, ratios))
+            })
+            .collect::<Option<_>>()
+            .ok_or(format!("Malformed tallyvotes of proposal {}.", proposal_id))?;
+        db::regproposalstats(db, proposal_id, &stats).await?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegacyTally {
+    pub proposal_id: u64,
+    pub height: u64,
+    pub stats: HashMap<String, (u64, u64)>,
+}
+
+// Rewrites stats maps of recorded tallies and proposals into typed options, oldest first,
+// so every proposal ends up with its latest tally.
+async fn typestats(db: &Surreal<Client>) -> Result<(), Box<dyn Error>> {
+    let height = db::toprec(db).await?.unwrap_or(tally::TALLY_START);
+    for legacy in db::getlegacytallies(db, height).await? {
+        let proposal = tally::from_legacy(legacy.proposal_id, legacy.height, &legacy.stats);
+        db::regproposal(db, &proposal).await?;
+    }
+    db::unsetlegacystats(db).await
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RingTx {
+    pub txid: String,
+    pub height: u64,
+}
+
+async fn keyimages(db: &Surreal<Client>, rpcurl: &RPCURL) -> Result<(), Box<dyn Error>> {
+    for ringtx in db::getringtxswithoutkeyimages(db).await? {
+        let Some(blockhash) = db::getblockhash(db, ringtx.height).await? else {
+            warn!("Block {} of {} isn't recorded.", ringtx.height, ringtx.txid);
+            continue;
+        };
+        let arg = format!("getrawtransaction {} false {}", ringtx.txid, blockhash);
+        let hex: String = serde_json::from_value(call(&arg, rpcurl)?)?;
+        match rings::key_images(&hex) {
+            Some(key_images) => db::regkeyimages(db, &ringtx.txid, &key_images).await?,
+            None => warn!("Failed to decode key images of {}.", ringtx.txid),
+        }
+    }
+    Ok(())
+}
+
+// Blocks whose cold-staking script was decoded natively didn't record their stakeaddress.
+async fn stakeaddresses(db: &Surreal<Client>) -> Result<(), Box<dyn Error>> {
+    for (raw, stakeonly) in db::getcoldstakeaddresses(db).await? {
+        let stakeaddress = Stakeaddress {
+            raw,
+            pool: pools::identify(&stakeonly),
+        };
+        db::regstakeaddress(db, &stakeaddress).await?;
+    }
+    Ok(())
+}
+
+// Blocks per query of the votes backfill.
+const VOTE_BATCH: u64 = 10000;
+
+// Votes are extracted by votes::record, the same way blocks are processed live.
+async fn votes(db: &Surreal<Client>) -> Result<(), Box<dyn Error>> {
+    let mut from = 0;
+    loop {
+        let blocks = db::getvotedblocks(db, from, VOTE_BATCH).await?;
+        let Some(last) = blocks.last() else {
+            return Ok(());
+        };
+        from = last.height + 1;
+        let votes: Vec<VoteRecord> = blocks.iter().filter_map(votes::record).collect();
+        db::regvotes(db, &votes).await?;
+    }
+}
+
+// Votes per replay query, which runs as a single transaction.
+const VOTER_BATCH: u64 = 10000;
+
+// Votes are replayed in order of height, so option changes are recorded as they happened.
+// Replays the votes table, so it depends on the votes backfill having run before it.
+async fn voters(db: &Surreal<Client>) -> Result<(), Box<dyn Error>> {
+    db::delvoters(db).await?;
+    let mut after = 0;
+    while let Some(height) = db::regvoters(db, after, VOTER_BATCH).await? {
+        after = height;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedBlock {
+    pub height: u64,
+    pub time: u64,
+    pub staked: bool,
+    pub proposal_id: Option<u64>,
+}
+
+// Blocks per query of the turnout replay.
+const TURNOUT_BATCH: u64 = 10000;
+
+// Blocks are replayed in order of height, so every window is complete when it's measured.
+async fn turnouts(db: &Surreal<Client>, window: u64) -> Result<(), Box<dyn Error>> {
+    db::delturnout(db).await?;
+    let mut from = 0;
+    loop {
+        let blocks = db::getrecordedblocks(db, from, TURNOUT_BATCH).await?;
+        let Some(last) = blocks.last() else {
+            return Ok(());
+        };
+        from = last.height + 1;
+        for block in blocks.iter() {
+            let turnout =
+                turnout::recorded(block.height, block.time, block.staked, block.proposal_id);
+            db::regturnout(db, &turnout, window).await?;
+        }
+    }
+}
+
+// Blocks per query of the amount conversion. Whole transactions are read and written back.
+const AMOUNT_BATCH: u64 = 1000;
+
+// Blocks stored before amounts were converted at the RPC boundary keep them as float coins.
+async fn blockamounts(db: &Surreal<Client>) -> Result<(), Box<dyn Error>> {
+    let mut from = 0;
+    loop {
+        let blocks = db::getblockamounts(db, from, AMOUNT_BATCH).await?;
+        let Some(last) = blocks.last().and_then(|block| block["height"].as_u64()) else {
+            return Ok(());
+        };
+        from = last + 1;
+        let converted: Vec<Value> = blocks
+            .into_iter()
+            .filter_map(|block| {
+                let mut converted = block.clone();
+                block_coins_to_sats(&mut converted);
+                (converted != block).then_some(converted)
+            })
+            .collect();
+        if !converted.is_empty() {
+            db::regblockamounts(db, &converted).await?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(step: fn(&Step) -> bool) -> usize {
+        MIGRATIONS.iter().position(|m| step(&m.step)).unwrap()
+    }
+
+    #[test]
+    fn migration_order() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as u64 + 1);
+        }
+        // Voters are rebuilt from the votes table, which the votes backfill completes.
+        assert!(
+            position(|step| matches!(step, Step::Votes))
+                < position(|step| matches!(step, Step::Voters))
+        );
+        // Weights of backfilled votes are read as satoshis.
+        assert!(
+            position(|step| matches!(step, Step::BlockAmounts))
+                < position(|step| matches!(step, Step::Votes))
+        );
+    }
+}
@@ -18,6 +18,18 @@ impl PrivatePool {
     }
 }
 
+// Known pool behind the stake-only address, or an anonymous one if there is none.
+pub fn identify(stakeonly_address: &str) -> Pool {
+    match POOLS.iter().find(|pool| pool.pubkey == stakeonly_address) {
+        Some(known_pool) => known_pool.getpool(),
+        None => Pool {
+            pubkey: stakeonly_address.to_string(),
+            url: None,
+            pool_is_active: None,
+        },
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 pub struct Pool {
     pub pubkey: String,
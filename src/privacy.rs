@@ -0,0 +1,199 @@
+// Usage statistics of confidential (blind) and RingCT (anon) transactions.
+use crate::{
+    console::{BlockData, Transaction, Vin, Vout},
+    utxo::{outpoint, Utxo},
+};
+use humantime::format_rfc3339_seconds as timestamp;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrivacyStats {
+    pub height: u64,
+    pub time: u64,
+    // UTC date, for example '2023-01-31'.
+    pub day: String,
+    pub standard_outputs: u64,
+    pub blind_outputs: u64,
+    pub anon_outputs: u64,
+    pub rangeproofs: u64,
+    pub rangeproof_bytes: u64,
+    // Transactions by the most private component among their inputs and outputs.
+    pub standard_txs: u64,
+    pub blind_txs: u64,
+    pub anon_txs: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Level {
+    Standard,
+    Blind,
+    Anon,
+}
+
+pub fn day(time: u64) -> String {
+    timestamp(UNIX_EPOCH + Duration::from_secs(time)).to_string()[..10].to_string()
+}
+
+fn output_level(vout: &Vout) -> Option<Level> {
+    match vout {
+        Vout::Standard { .. } => Some(Level::Standard),
+        Vout::Blind { .. } => Some(Level::Blind),
+        Vout::Anon { .. } => Some(Level::Anon),
+        Vout::Data { .. } | Vout::Unknown(_) => None,
+    }
+}
+
+fn tx_level(tx: &Transaction, spent: &[Utxo]) -> Level {
+    let inputs = tx.vin.iter().map(|vin| match vin {
+        Vin::Anon { .. } => Level::Anon,
+        Vin::Standard { txid, vout, .. } => {
+            let key = outpoint(txid, *vout);
+            match spent.iter().find(|utxo| utxo.outpoint == key) {
+                Some(utxo) if utxo.valuesat.is_none() => Level::Blind,
+                _ => Level::Standard,
+            }
+        }
+        Vin::Coinbase { .. } | Vin::Unknown(_) => Level::Standard,
+    });
+    let outputs = tx.vout.iter().filter_map(output_level);
+    inputs.chain(outputs).fold(
+        Level::Standard,
+        |acc, level| if level > acc { level } else { acc },
+    )
+}
+
+pub fn stats(blockdata: &BlockData, spent: &[Utxo]) -> PrivacyStats {
+    let mut stats = PrivacyStats {
+        height: blockdata.height,
+        time: blockdata.time,
+        day: day(blockdata.time),
+        standard_outputs: 0,
+        blind_outputs: 0,
+        anon_outputs: 0,
+        rangeproofs: 0,
+        rangeproof_bytes: 0,
+        standard_txs: 0,
+        blind_txs: 0,
+        anon_txs: 0,
+    };
+    for tx in blockdata.tx.iter() {
+        match tx_level(tx, spent) {
+            Level::Standard => stats.standard_txs += 1,
+            Level::Blind => stats.blind_txs += 1,
+            Level::Anon => stats.anon_txs += 1,
+        }
+        for vout in tx.vout.iter() {
+            match output_level(vout) {
+                Some(Level::Standard) => stats.standard_outputs += 1,
+                Some(Level::Blind) => stats.blind_outputs += 1,
+                Some(Level::Anon) => stats.anon_outputs += 1,
+                None => {}
+            }
+            if let Vout::Blind { rangeproof, .. } | Vout::Anon { rangeproof, .. } = vout {
+                stats.rangeproofs += 1;
+                stats.rangeproof_bytes += rangeproof.len() as u64 / 2;
+            }
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::tests::{blockdata, standard_vin, standard_vout, tx};
+    use serde_json::{json, Value};
+
+    fn utxo(txid: &str, valuesat: Option<u64>) -> Utxo {
+        Utxo {
+            outpoint: outpoint(txid, 0),
+            txid: txid.to_string(),
+            n: 0,
+            height: 0,
+            output_type: "standard".to_string(),
+            valuesat,
+            value_commitment: None,
+            addresses: None,
+            stakeaddresses: None,
+            template: None,
+            coldstake: None,
+            spent: None,
+        }
+    }
+
+    fn confidential_vout(n: u64, output_type: &str, rangeproof: &str) -> Value {
+        json!({
+            "n": n,
+            "type": output_type,
+            "pubkey": "03",
+            "valueCommitment": "08",
+            "data_hex": "",
+            "rangeproof": rangeproof,
+        })
+    }
+
+    #[test]
+    fn day_is_the_utc_date() {
+        let cases = [
+            (0, "1970-01-01"),
+            (1_600_000_000, "2020-09-13"),
+            (86_399, "1970-01-01"),
+        ];
+        for (time, expected) in cases {
+            assert_eq!(day(time), expected);
+        }
+    }
+
+    #[test]
+    fn stats_count_outputs_and_rank_transactions_by_privacy() {
+        let anon_vin = json!({
+            "type": "anon",
+            "num_inputs": 1,
+            "ring_size": 3,
+            "txinwitness": [],
+            "sequence": 4294967295u64,
+        });
+        let blockdata = blockdata(
+            0,
+            vec![
+                tx(
+                    "public",
+                    json!([standard_vin("public", 0)]),
+                    json!([standard_vout(0, "a", 1)]),
+                ),
+                // Spending a blind output makes the transaction blind, even with public outputs only.
+                tx(
+                    "unblinding",
+                    json!([standard_vin("blind", 0)]),
+                    json!([standard_vout(0, "a", 1)]),
+                ),
+                tx(
+                    "ring",
+                    json!([anon_vin]),
+                    json!([
+                        {"n": 0, "type": "data", "data_hex": "", "ct_fee": 0.0001},
+                        confidential_vout(1, "blind", "aabb"),
+                        confidential_vout(2, "anon", "aabbcc"),
+                    ]),
+                ),
+            ],
+        );
+        let spent = [utxo("public", Some(1)), utxo("blind", None)];
+        let stats = stats(&blockdata, &spent);
+        assert_eq!(stats.day, "2020-09-13");
+        assert_eq!(
+            (
+                stats.standard_outputs,
+                stats.blind_outputs,
+                stats.anon_outputs
+            ),
+            (2, 1, 1)
+        );
+        assert_eq!((stats.rangeproofs, stats.rangeproof_bytes), (2, 5));
+        assert_eq!(
+            (stats.standard_txs, stats.blind_txs, stats.anon_txs),
+            (1, 1, 1)
+        );
+    }
+}
@@ -0,0 +1,152 @@
+// Proposal metadata from a local registry and lifecycle derived from it.
+use crate::{
+    console::Proposal,
+    tally::{self, OptionVotes},
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, error::Error};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProposalMeta {
+    pub proposal_id: u64,
+    pub title: String,
+    pub url: Option<String>,
+    // Labels keyed by option number.
+    #[serde(default)]
+    pub options: BTreeMap<u64, String>,
+    pub start_height: Option<u64>,
+    pub end_height: Option<u64>,
+    // Share of the blocks within the voting period, in basis points,
+    // which has to vote for the passing option.
+    pub threshold: Option<u64>,
+    #[serde(default = "default_passing_option")]
+    pub passing_option: u64,
+}
+
+fn default_passing_option() -> u64 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Lifecycle {
+    Pending,
+    Active,
+    // Voting period is over and there is no threshold to judge it by.
+    Ended,
+    Passed,
+    Failed,
+}
+
+// Registry is a JSON array of proposals, or [[proposals]] tables of a file named *.toml.
+// A broken registry is fatal, so it's never silently ignored.
+pub fn load(path: &str) -> Vec<ProposalMeta> {
+    let content = std::fs::read_to_string(path).unwrap_or_else(|e| {
+        error!("Failed to read proposal registry {}: {}", path, e);
+        std::process::exit(1);
+    });
+    parse(path, &content).unwrap_or_else(|e| {
+        error!("Failed to parse proposal registry {}: {}", path, e);
+        std::process::exit(1);
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct TomlRegistry {
+    proposals: Vec<serde_json::Value>,
+}
+
+fn parse(path: &str, content: &str) -> Result<Vec<ProposalMeta>, Box<dyn Error>> {
+    if !path.ends_with(".toml") {
+        return Ok(serde_json::from_str(content)?);
+    }
+    // Through JSON, since TOML keys are always strings and option labels are keyed by numbers.
+    let registry: TomlRegistry = toml::from_str(content)?;
+    Ok(serde_json::from_value(registry.proposals.into())?)
+}
+
+// Voting period of a proposal which is over and has a threshold to be judged by.
+pub fn closed_period(meta: &ProposalMeta, height: u64) -> Option<(u64, u64)> {
+    meta.threshold?;
+    let end = meta.end_height.filter(|end| height > *end)?;
+    Some((meta.start_height.unwrap_or(tally::TALLY_START), end))
+}
+
+// Votes are those cast within the closed period, if there is one.
+pub fn lifecycle(meta: &ProposalMeta, height: u64, votes: &[OptionVotes]) -> Lifecycle {
+    if meta.start_height.is_some_and(|start| height < start) {
+        return Lifecycle::Pending;
+    }
+    if meta.end_height.is_none_or(|end| height <= end) {
+        return Lifecycle::Active;
+    }
+    let (start, end) = match closed_period(meta, height) {
+        Some(period) => period,
+        None => return Lifecycle::Ended,
+    };
+    let result = tally::tally(meta.proposal_id, start, end, votes);
+    let share = result
+        .options
+        .iter()
+        .find(|option| option.option == meta.passing_option)
+        .map_or(0, |option| option.share);
+    match share >= meta.threshold.unwrap() {
+        true => Lifecycle::Passed,
+        false => Lifecycle::Failed,
+    }
+}
+
+// Names options of the proposal after the labels in the registry.
+pub fn label(proposal: &mut Proposal, registry: &[ProposalMeta]) {
+    let meta = match registry
+        .iter()
+        .find(|meta| meta.proposal_id == proposal.proposal_id)
+    {
+        Some(meta) => meta,
+        None => return,
+    };
+    for option in proposal.options.iter_mut() {
+        option.label = meta.options.get(&option.option).cloned();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_and_toml_registries_agree() {
+        let json = r#"[
+            { "proposal_id": 5, "title": "Example", "url": "https://example.org/5",
+              "options": { "1": "Yes", "2": "No" },
+              "start_height": 1490000, "end_height": 1510000, "threshold": 5000 },
+            { "proposal_id": 6, "title": "Bare", "passing_option": 2 }
+        ]"#;
+        let toml = r#"
+            [[proposals]]
+            proposal_id = 5
+            title = "Example"
+            url = "https://example.org/5"
+            options = { 1 = "Yes", 2 = "No" }
+            start_height = 1490000
+            end_height = 1510000
+            threshold = 5000
+
+            [[proposals]]
+            proposal_id = 6
+            title = "Bare"
+            passing_option = 2
+        "#;
+        let from_json = parse("registry.json", json).unwrap();
+        let from_toml = parse("registry.toml", toml).unwrap();
+        assert_eq!(
+            serde_json::to_value(&from_json).unwrap(),
+            serde_json::to_value(&from_toml).unwrap()
+        );
+        assert_eq!(from_toml[0].options.get(&2).map(String::as_str), Some("No"));
+        assert_eq!(from_toml[0].passing_option, 1);
+        assert_eq!(from_toml[1].passing_option, 2);
+        assert!(parse("registry.toml", json).is_err());
+        assert!(parse("registry.json", toml).is_err());
+    }
+}
@@ -0,0 +1,195 @@
+// Decomposition of the coinstake transaction into stake and reward parts.
+use crate::{
+    console::{BlockData, Vin, Vout},
+    treasury::is_treasury,
+    utxo::{outpoint, Utxo},
+};
+use serde::{Deserialize, Serialize};
+
+// All amounts are in satoshis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoinstakeReward {
+    pub staked: u64,
+    pub outputs: u64,
+    // Outputs minus inputs, so it includes the transaction fees of the block.
+    pub reward: u64,
+    // Part of the reward returned to the staker's own outputs.
+    pub staker: i64,
+    pub treasury: u64,
+    pub pool_fee: u64,
+    pub staker_address: Option<String>,
+    pub pool_fee_address: Option<String>,
+}
+
+// Returns None for proof of work blocks and coinstakes spending unknown outputs.
+pub fn decompose(blockdata: &BlockData, spent: &[Utxo]) -> Option<CoinstakeReward> {
+    let coinstake = blockdata.tx.first()?;
+    if !matches!(coinstake.vout.first(), Some(Vout::Data { .. })) {
+        return None;
+    }
+    let mut inputs: Vec<&Utxo> = Vec::new();
+    for vin in coinstake.vin.iter() {
+        match vin {
+            Vin::Standard { txid, vout, .. } => {
+                let key = outpoint(txid, *vout);
+                inputs.push(spent.iter().find(|utxo| utxo.outpoint == key)?);
+            }
+            _ => return None,
+        }
+    }
+    let staked: u64 = inputs
+        .iter()
+        .map(|utxo| utxo.valuesat)
+        .sum::<Option<u64>>()?;
+    // Stakes may combine outputs of several addresses, and each of them gets its part back.
+    let staker_addresses: Vec<&String> = inputs
+        .iter()
+        .filter_map(|utxo| utxo.addresses.as_ref()?.first())
+        .collect();
+    let staker_address = staker_addresses.first().map(|address| address.to_string());
+    let mut decomposed = CoinstakeReward {
+        staked,
+        outputs: 0,
+        reward: 0,
+        staker: 0,
+        treasury: 0,
+        pool_fee: 0,
+        staker_address,
+        pool_fee_address: None,
+    };
+    let mut returned: u64 = 0;
+    for vout in coinstake.vout.iter() {
+        if let Vout::Standard {
+            valuesat,
+            scriptpubkey,
+            ..
+        } = vout
+        {
+            decomposed.outputs += valuesat;
+            let address = scriptpubkey
+                .addresses
+                .as_ref()
+                .and_then(|addresses| addresses.first());
+            match address {
+                Some(address) if is_treasury(address) => decomposed.treasury += valuesat,
+                Some(address) if staker_addresses.contains(&address) => returned += valuesat,
+                _ => {
+                    decomposed.pool_fee += valuesat;
+                    decomposed.pool_fee_address = address.cloned();
+                }
+            }
+        }
+    }
+    if decomposed.outputs < staked {
+        warn!(
+            "Coinstake of block {} pays out less than it stakes.",
+            blockdata.height
+        );
+        return None;
+    }
+    decomposed.reward = decomposed.outputs - staked;
+    decomposed.staker = returned as i64 - staked as i64;
+    Some(decomposed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        console::tests::{blockdata, standard_vin, standard_vout, tx},
+        treasury::TREASURY_ADDRESSES,
+    };
+    use serde_json::{json, Value};
+
+    fn utxo(txid: &str, address: &str, valuesat: u64) -> Utxo {
+        Utxo {
+            outpoint: outpoint(txid, 0),
+            txid: txid.to_string(),
+            n: 0,
+            height: 0,
+            output_type: "standard".to_string(),
+            valuesat: Some(valuesat),
+            value_commitment: None,
+            addresses: Some(vec![address.to_string()]),
+            stakeaddresses: None,
+            template: None,
+            coldstake: None,
+            spent: None,
+        }
+    }
+
+    fn coinstake(inputs: &[&str], outputs: &[(&str, u64)]) -> BlockData {
+        let vin: Vec<Value> = inputs.iter().map(|txid| standard_vin(txid, 0)).collect();
+        let mut vout = vec![json!({"n": 0, "type": "data", "data_hex": ""})];
+        for (n, (address, valuesat)) in outputs.iter().enumerate() {
+            vout.push(standard_vout(n as u64 + 1, address, *valuesat));
+        }
+        blockdata(1, vec![tx("coinstake", json!(vin), json!(vout))])
+    }
+
+    fn parts(reward: &CoinstakeReward) -> (u64, u64, u64, i64, u64, u64) {
+        (
+            reward.staked,
+            reward.outputs,
+            reward.reward,
+            reward.staker,
+            reward.treasury,
+            reward.pool_fee,
+        )
+    }
+
+    #[test]
+    fn decompose_cases() {
+        let treasury = TREASURY_ADDRESSES[0];
+        let spent = [utxo("a", "staker", 1_000), utxo("b", "other", 500)];
+        // (inputs, outputs, expected staked, outputs, reward, staker, treasury and pool fee)
+        let cases = [
+            (
+                vec!["a"],
+                vec![("staker", 1_100)],
+                (1_000, 1_100, 100, 100, 0, 0),
+            ),
+            (
+                vec!["a"],
+                vec![("staker", 1_080), (treasury, 20)],
+                (1_000, 1_100, 100, 80, 20, 0),
+            ),
+            (
+                vec!["a"],
+                vec![("staker", 1_090), ("pool", 10)],
+                (1_000, 1_100, 100, 90, 0, 10),
+            ),
+            (
+                vec!["a", "b"],
+                vec![("staker", 1_050), ("other", 550)],
+                (1_500, 1_600, 100, 100, 0, 0),
+            ),
+        ];
+        for (inputs, outputs, expected) in cases {
+            let decomposed = decompose(&coinstake(&inputs, &outputs), &spent).unwrap();
+            assert_eq!(parts(&decomposed), expected, "{:?}", outputs);
+            assert_eq!(decomposed.staker_address.as_deref(), Some("staker"));
+            let pool_fee_address = outputs
+                .iter()
+                .find(|(address, _)| *address == "pool")
+                .map(|(address, _)| address.to_string());
+            assert_eq!(decomposed.pool_fee_address, pool_fee_address);
+        }
+    }
+
+    #[test]
+    fn decompose_needs_resolved_inputs_and_a_coinstake() {
+        let spent = [utxo("a", "staker", 1_000)];
+        assert!(decompose(&coinstake(&["missing"], &[("staker", 1_100)]), &spent).is_none());
+        assert!(decompose(&coinstake(&["a"], &[("staker", 900)]), &spent).is_none());
+        let coinbase = blockdata(
+            1,
+            vec![tx(
+                "coinbase",
+                json!([]),
+                json!([standard_vout(0, "miner", 1_000)]),
+            )],
+        );
+        assert!(decompose(&coinbase, &spent).is_none());
+    }
+}
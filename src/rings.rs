@@ -0,0 +1,245 @@
+// RingCT ring usage decoded from anon inputs.
+use crate::console::{BlockData, Vin};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RingInput {
+    pub txid: String,
+    // Position of the input within the transaction.
+    pub input: u64,
+    pub height: u64,
+    pub time: u64,
+    pub num_inputs: u64,
+    pub ring_size: u64,
+    // Global indices of the anon outputs used as ring members, ring_size per signed input.
+    pub members: Vec<u64>,
+    // One per signed input, hex encoded.
+    pub key_images: Vec<String>,
+    pub signature_bytes: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RingStats {
+    pub height: u64,
+    pub time: u64,
+    pub anon_txs: u64,
+    pub anon_inputs: u64,
+    // Number of signed inputs per ring size.
+    pub ring_sizes: BTreeMap<String, u64>,
+}
+
+// Ring member indices are serialized as consecutive LEB128 varints.
+fn decode_members(witness: &str) -> Option<Vec<u64>> {
+    let bytes = hex::decode(witness).ok()?;
+    let mut members = Vec::new();
+    let mut value: u64 = 0;
+    let mut shift = 0;
+    for byte in bytes {
+        if shift > 63 {
+            return None;
+        }
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            members.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    if shift != 0 {
+        return None;
+    }
+    Some(members)
+}
+
+// Reads a Bitcoin style CompactSize length.
+fn compact_size(bytes: &[u8], pos: &mut usize) -> Option<usize> {
+    let width = match *bytes.get(*pos)? {
+        0xfd => 2,
+        0xfe => 4,
+        0xff => 8,
+        size => {
+            *pos += 1;
+            return Some(size as usize);
+        }
+    };
+    let le = bytes.get(*pos + 1..*pos + 1 + width)?;
+    *pos += 1 + width;
+    Some(
+        le.iter()
+            .rev()
+            .fold(0, |acc, byte| acc << 8 | *byte as usize),
+    )
+}
+
+fn skip(bytes: &[u8], pos: &mut usize, len: usize) -> Option<()> {
+    *pos = pos.checked_add(len).filter(|end| *end <= bytes.len())?;
+    Some(())
+}
+
+// getblock doesn't list the script data of anon inputs, which holds their key images,
+// so they are read from the raw transaction. Particl transactions serialize as version,
+// type, locktime and inputs, where an anon input is marked by the 0xffffffa0 prevout index
+// and followed by its script data stack. The first stack item is the 33 byte key image of
+// every signed input. Returns the key images of each input, empty for other inputs.
+pub fn key_images(tx_hex: &str) -> Option<Vec<Vec<String>>> {
+    let bytes = hex::decode(tx_hex).ok()?;
+    let mut pos = 0;
+    skip(&bytes, &mut pos, 6)?;
+    let vin = compact_size(&bytes, &mut pos)?;
+    let mut images = Vec::new();
+    for _ in 0..vin {
+        skip(&bytes, &mut pos, 32)?;
+        let index = bytes.get(pos..pos + 4)?;
+        let anon = index == [0xa0, 0xff, 0xff, 0xff];
+        skip(&bytes, &mut pos, 4)?;
+        let script_sig = compact_size(&bytes, &mut pos)?;
+        skip(&bytes, &mut pos, script_sig + 4)?;
+        if !anon {
+            images.push(Vec::new());
+            continue;
+        }
+        let mut stack = Vec::new();
+        for _ in 0..compact_size(&bytes, &mut pos)? {
+            let len = compact_size(&bytes, &mut pos)?;
+            stack.push(bytes.get(pos..pos + len)?);
+            skip(&bytes, &mut pos, len)?;
+        }
+        let data = stack.first()?;
+        if data.len() % 33 != 0 {
+            return None;
+        }
+        images.push(data.chunks(33).map(hex::encode).collect());
+    }
+    Some(images)
+}
+
+pub fn inputs(blockdata: &BlockData) -> Vec<RingInput> {
+    let mut inputs = Vec::new();
+    for tx in blockdata.tx.iter() {
+        let mut images = match tx.vin.iter().any(|vin| matches!(vin, Vin::Anon { .. })) {
+            true => key_images(&tx.hex).unwrap_or_else(|| {
+                warn!("Failed to decode key images of {}.", tx.txid);
+                Vec::new()
+            }),
+            false => Vec::new(),
+        };
+        for (i, vin) in tx.vin.iter().enumerate() {
+            if let Vin::Anon {
+                num_inputs,
+                ring_size,
+                txinwitness,
+                ..
+            } = vin
+            {
+                let members = txinwitness
+                    .first()
+                    .and_then(|witness| decode_members(witness))
+                    .unwrap_or_else(|| {
+                        warn!("Failed to decode ring members of {} input {}.", tx.txid, i);
+                        Vec::new()
+                    });
+                if !members.is_empty() && members.len() as u64 != num_inputs * ring_size {
+                    warn!(
+                        "Input {} of {} references {} ring members, expected {}.",
+                        i,
+                        tx.txid,
+                        members.len(),
+                        num_inputs * ring_size
+                    );
+                }
+                let key_images = images.get_mut(i).map(std::mem::take).unwrap_or_default();
+                if !key_images.is_empty() && key_images.len() as u64 != *num_inputs {
+                    warn!(
+                        "Input {} of {} carries {} key images, expected {}.",
+                        i,
+                        tx.txid,
+                        key_images.len(),
+                        num_inputs
+                    );
+                }
+                inputs.push(RingInput {
+                    txid: tx.txid.clone(),
+                    input: i as u64,
+                    height: blockdata.height,
+                    time: blockdata.time,
+                    num_inputs: *num_inputs,
+                    ring_size: *ring_size,
+                    members,
+                    key_images,
+                    signature_bytes: txinwitness.get(1).map_or(0, |sig| sig.len() as u64 / 2),
+                });
+            }
+        }
+    }
+    inputs
+}
+
+pub fn stats(blockdata: &BlockData, inputs: &[RingInput]) -> RingStats {
+    let mut stats = RingStats {
+        height: blockdata.height,
+        time: blockdata.time,
+        anon_txs: 0,
+        anon_inputs: 0,
+        ring_sizes: BTreeMap::new(),
+    };
+    let mut txids: Vec<&String> = inputs.iter().map(|input| &input.txid).collect();
+    txids.dedup();
+    stats.anon_txs = txids.len() as u64;
+    for input in inputs.iter() {
+        stats.anon_inputs += input.num_inputs;
+        *stats
+            .ring_sizes
+            .entry(input.ring_size.to_string())
+            .or_default() += input.num_inputs;
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_members_reads_leb128() {
+        let cases = [
+            ("", Some(vec![])),
+            ("00", Some(vec![0])),
+            ("7f", Some(vec![127])),
+            ("8001", Some(vec![128])),
+            ("ac02", Some(vec![300])),
+            ("01ac0202", Some(vec![1, 300, 2])),
+            ("e58e26", Some(vec![624_485])),
+            ("ffffffffffffffffff01", Some(vec![u64::MAX])),
+            // Truncated varint.
+            ("0180", None),
+            // Longer than 64 bits.
+            ("8080808080808080808001", None),
+            ("not hex", None),
+        ];
+        for (witness, expected) in cases {
+            assert_eq!(decode_members(witness), expected, "{}", witness);
+        }
+    }
+
+    #[test]
+    fn key_images_are_read_from_anon_inputs() {
+        let first = "02".to_string() + &"aa".repeat(32);
+        let second = "03".to_string() + &"bb".repeat(32);
+        let standard = "11".repeat(32) + "01000000" + "00" + "ffffffff";
+        let anon =
+            "22".repeat(32) + "a0ffffff" + "00" + "ffffffff" + "01" + "42" + &first + &second;
+        let tx = "a000".to_string() + "00000000" + "02" + &standard + &anon + "00";
+        assert_eq!(key_images(&tx), Some(vec![vec![], vec![first, second]]));
+        // Truncated within the script data.
+        assert_eq!(key_images(&tx[..tx.len() - 40]), None);
+        // Script data which isn't a whole number of key images.
+        let odd = "22".repeat(32) + "a0ffffff" + "00" + "ffffffff" + "01" + "02" + "abcd";
+        assert_eq!(
+            key_images(&("a000".to_string() + "00000000" + "01" + &odd)),
+            None
+        );
+    }
+}
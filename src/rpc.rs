@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::error::Error;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Default)]
 pub struct RPCURL(String);
 
@@ -16,14 +17,14 @@ impl RPCURL {
         password: &str,
     ) -> Self {
         trace!("Constructing RPC console URL ...");
-        if walletname.len() == 0 {
-            if user != "" && password != "" {
+        if walletname.is_empty() {
+            if !user.is_empty() && !password.is_empty() {
                 self.0 = format!("http://{}:{}@{}:{}/", user, password, ip, port);
             } else {
                 self.0 = format!("http://{}:{}/", ip, port);
             }
         } else {
-            if user != "" && password != "" {
+            if !user.is_empty() && !password.is_empty() {
                 self.0 = format!(
                     "http://{}:{}@{}:{}/wallet/{}",
                     user, password, ip, port, walletname
@@ -32,7 +33,7 @@ impl RPCURL {
                 self.0 = format!("http://{}:{}/wallet/{}", ip, port, walletname);
             }
         }
-        return self;
+        self
     }
 }
 
@@ -49,7 +50,7 @@ fn parametrize(args: &str) -> Vec<Value> {
             }
         }
     }
-    return params;
+    params
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -95,5 +96,5 @@ pub(crate) fn call(args: &str, rpcurl: &RPCURL) -> Result<Value, Box<dyn Error>>
         .set("Content-Type", "application/json")
         .send_json(serde_json::to_value(post)?)?
         .into_json()?;
-    return Ok(response["result"].clone());
+    Ok(response["result"].clone())
 }
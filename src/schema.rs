@@ -0,0 +1,264 @@
+// SurrealDB table, field and index definitions.
+// Applied as the first migration; later changes go into new migrations.
+
+// Tables stay schemaless, so nested block data doesn't need to be spelled out,
+// but fields used for lookups are typed and indexed.
+pub const SCHEMA: &str = "
+DEFINE TABLE OVERWRITE blocks SCHEMALESS;
+DEFINE FIELD OVERWRITE hash ON blocks TYPE string;
+DEFINE FIELD OVERWRITE height ON blocks TYPE int;
+DEFINE FIELD OVERWRITE time ON blocks TYPE int;
+DEFINE FIELD OVERWRITE mediantime ON blocks TYPE int;
+DEFINE FIELD OVERWRITE previousblockhash ON blocks TYPE option<string>;
+DEFINE FIELD OVERWRITE coldstaking ON blocks TYPE option<object>;
+DEFINE FIELD OVERWRITE coldstaking.pubkey ON blocks TYPE option<string>;
+DEFINE FIELD OVERWRITE voting_info ON blocks TYPE option<object>;
+DEFINE FIELD OVERWRITE voting_info.proposal_id ON blocks TYPE option<int>;
+DEFINE FIELD OVERWRITE voting_info.voted_for_option ON blocks TYPE option<int>;
+DEFINE FIELD OVERWRITE coinstake ON blocks TYPE option<object>;
+DEFINE INDEX OVERWRITE blocks_hash ON blocks FIELDS hash UNIQUE;
+DEFINE INDEX OVERWRITE blocks_height ON blocks FIELDS height UNIQUE;
+DEFINE INDEX OVERWRITE blocks_time ON blocks FIELDS time;
+DEFINE INDEX OVERWRITE blocks_coldstaking ON blocks FIELDS coldstaking.pubkey;
+DEFINE INDEX OVERWRITE blocks_proposal ON blocks FIELDS voting_info.proposal_id;
+
+DEFINE TABLE OVERWRITE proposals SCHEMALESS;
+DEFINE FIELD OVERWRITE proposal_id ON proposals TYPE int;
+DEFINE FIELD OVERWRITE stats ON proposals TYPE object;
+DEFINE INDEX OVERWRITE proposals_id ON proposals FIELDS proposal_id UNIQUE;
+
+DEFINE TABLE OVERWRITE stakeaddresses SCHEMALESS;
+DEFINE FIELD OVERWRITE raw ON stakeaddresses TYPE string;
+DEFINE FIELD OVERWRITE pool ON stakeaddresses TYPE object;
+DEFINE FIELD OVERWRITE pool.pubkey ON stakeaddresses TYPE string;
+DEFINE INDEX OVERWRITE stakeaddresses_pool ON stakeaddresses FIELDS pool.pubkey;
+
+DEFINE TABLE OVERWRITE zmq SCHEMALESS;
+DEFINE FIELD OVERWRITE blocks ON zmq TYPE array<string>;
+
+DEFINE TABLE OVERWRITE utxos SCHEMALESS;
+DEFINE FIELD OVERWRITE outpoint ON utxos TYPE string;
+DEFINE FIELD OVERWRITE txid ON utxos TYPE string;
+DEFINE FIELD OVERWRITE n ON utxos TYPE int;
+DEFINE FIELD OVERWRITE height ON utxos TYPE int;
+DEFINE FIELD OVERWRITE valuesat ON utxos TYPE option<int>;
+DEFINE FIELD OVERWRITE addresses ON utxos TYPE option<array<string>>;
+DEFINE FIELD OVERWRITE spent ON utxos TYPE option<object>;
+DEFINE FIELD OVERWRITE spent.txid ON utxos TYPE option<string>;
+DEFINE FIELD OVERWRITE spent.height ON utxos TYPE option<int>;
+DEFINE INDEX OVERWRITE utxos_height ON utxos FIELDS height;
+DEFINE INDEX OVERWRITE utxos_spent ON utxos FIELDS spent.height;
+DEFINE INDEX OVERWRITE utxos_addresses ON utxos FIELDS addresses;
+
+DEFINE TABLE OVERWRITE balances SCHEMALESS;
+DEFINE FIELD OVERWRITE address ON balances TYPE string;
+DEFINE FIELD OVERWRITE height ON balances TYPE int;
+DEFINE FIELD OVERWRITE balance ON balances TYPE int;
+DEFINE FIELD OVERWRITE delta ON balances TYPE int;
+DEFINE INDEX OVERWRITE balances_balance ON balances FIELDS balance;
+
+DEFINE TABLE OVERWRITE balancehistory SCHEMALESS;
+DEFINE FIELD OVERWRITE address ON balancehistory TYPE string;
+DEFINE FIELD OVERWRITE height ON balancehistory TYPE int;
+DEFINE FIELD OVERWRITE balance ON balancehistory TYPE int;
+DEFINE FIELD OVERWRITE delta ON balancehistory TYPE int;
+DEFINE INDEX OVERWRITE balancehistory_address ON balancehistory FIELDS address, height;
+DEFINE INDEX OVERWRITE balancehistory_height ON balancehistory FIELDS height;
+
+DEFINE TABLE OVERWRITE fees SCHEMALESS;
+DEFINE FIELD OVERWRITE txid ON fees TYPE string;
+DEFINE FIELD OVERWRITE height ON fees TYPE int;
+DEFINE FIELD OVERWRITE fee ON fees TYPE option<int>;
+DEFINE FIELD OVERWRITE feerate ON fees TYPE option<float>;
+DEFINE FIELD OVERWRITE unknowable ON fees TYPE option<string>;
+DEFINE INDEX OVERWRITE fees_height ON fees FIELDS height;
+
+DEFINE TABLE OVERWRITE blockfees SCHEMALESS;
+DEFINE FIELD OVERWRITE height ON blockfees TYPE int;
+DEFINE FIELD OVERWRITE time ON blockfees TYPE int;
+DEFINE FIELD OVERWRITE total ON blockfees TYPE int;
+DEFINE INDEX OVERWRITE blockfees_height ON blockfees FIELDS height UNIQUE;
+
+DEFINE TABLE OVERWRITE treasury SCHEMALESS;
+DEFINE FIELD OVERWRITE height ON treasury TYPE int;
+DEFINE FIELD OVERWRITE time ON treasury TYPE int;
+DEFINE FIELD OVERWRITE cfwd ON treasury TYPE int;
+DEFINE FIELD OVERWRITE payout ON treasury TYPE int;
+DEFINE FIELD OVERWRITE contribution ON treasury TYPE int;
+DEFINE FIELD OVERWRITE balance ON treasury TYPE int;
+DEFINE INDEX OVERWRITE treasury_height ON treasury FIELDS height UNIQUE;
+
+DEFINE TABLE OVERWRITE smsg SCHEMALESS;
+DEFINE FIELD OVERWRITE height ON smsg TYPE int;
+DEFINE FIELD OVERWRITE time ON smsg TYPE int;
+DEFINE FIELD OVERWRITE feerate ON smsg TYPE option<int>;
+DEFINE FIELD OVERWRITE difficulty ON smsg TYPE option<string>;
+DEFINE INDEX OVERWRITE smsg_height ON smsg FIELDS height UNIQUE;
+
+DEFINE TABLE OVERWRITE privacy SCHEMALESS;
+DEFINE FIELD OVERWRITE height ON privacy TYPE int;
+DEFINE FIELD OVERWRITE time ON privacy TYPE int;
+DEFINE FIELD OVERWRITE day ON privacy TYPE string;
+DEFINE INDEX OVERWRITE privacy_height ON privacy FIELDS height UNIQUE;
+DEFINE INDEX OVERWRITE privacy_day ON privacy FIELDS day;
+
+DEFINE TABLE OVERWRITE privacydaily SCHEMALESS;
+DEFINE FIELD OVERWRITE day ON privacydaily TYPE string;
+DEFINE FIELD OVERWRITE blocks ON privacydaily TYPE int;
+
+DEFINE TABLE OVERWRITE rings SCHEMALESS;
+DEFINE FIELD OVERWRITE txid ON rings TYPE string;
+DEFINE FIELD OVERWRITE height ON rings TYPE int;
+DEFINE FIELD OVERWRITE ring_size ON rings TYPE int;
+DEFINE FIELD OVERWRITE members ON rings TYPE array<int>;
+DEFINE INDEX OVERWRITE rings_height ON rings FIELDS height;
+DEFINE INDEX OVERWRITE rings_ring_size ON rings FIELDS ring_size;
+
+DEFINE TABLE OVERWRITE ringblocks SCHEMALESS;
+DEFINE FIELD OVERWRITE height ON ringblocks TYPE int;
+DEFINE INDEX OVERWRITE ringblocks_height ON ringblocks FIELDS height UNIQUE;
+
+DEFINE TABLE OVERWRITE unknownshapes SCHEMALESS;
+DEFINE FIELD OVERWRITE height ON unknownshapes TYPE int;
+DEFINE FIELD OVERWRITE kind ON unknownshapes TYPE string ASSERT $value IN ['vin', 'vout'];
+DEFINE INDEX OVERWRITE unknownshapes_height ON unknownshapes FIELDS height;
+
+DEFINE TABLE OVERWRITE delegations SCHEMALESS;
+DEFINE FIELD OVERWRITE spend ON delegations TYPE string;
+DEFINE FIELD OVERWRITE stakeonly ON delegations TYPE string;
+DEFINE FIELD OVERWRITE value ON delegations TYPE int;
+DEFINE INDEX OVERWRITE delegations_stakeonly ON delegations FIELDS stakeonly;
+
+DEFINE TABLE OVERWRITE delegationchanges SCHEMALESS;
+DEFINE FIELD OVERWRITE spend ON delegationchanges TYPE string;
+DEFINE FIELD OVERWRITE stakeonly ON delegationchanges TYPE string;
+DEFINE FIELD OVERWRITE height ON delegationchanges TYPE int;
+DEFINE INDEX OVERWRITE delegationchanges_pair ON delegationchanges FIELDS spend, stakeonly;
+DEFINE INDEX OVERWRITE delegationchanges_height ON delegationchanges FIELDS height;
+
+DEFINE TABLE OVERWRITE stakes SCHEMALESS;
+DEFINE FIELD OVERWRITE height ON stakes TYPE int;
+DEFINE FIELD OVERWRITE time ON stakes TYPE int;
+DEFINE FIELD OVERWRITE staker ON stakes TYPE string;
+DEFINE FIELD OVERWRITE kind ON stakes TYPE string ASSERT $value IN ['hot', 'cold'];
+DEFINE FIELD OVERWRITE reward ON stakes TYPE int;
+DEFINE INDEX OVERWRITE stakes_height ON stakes FIELDS height UNIQUE;
+DEFINE INDEX OVERWRITE stakes_staker ON stakes FIELDS staker;
+
+DEFINE TABLE OVERWRITE stakers SCHEMALESS;
+DEFINE FIELD OVERWRITE staker ON stakers TYPE string;
+DEFINE FIELD OVERWRITE blocks ON stakers TYPE int;
+DEFINE INDEX OVERWRITE stakers_blocks ON stakers FIELDS blocks;
+
+DEFINE TABLE OVERWRITE decentralization SCHEMALESS;
+DEFINE FIELD OVERWRITE height ON decentralization TYPE int;
+DEFINE FIELD OVERWRITE window ON decentralization TYPE int;
+DEFINE INDEX OVERWRITE decentralization_height ON decentralization FIELDS height, window UNIQUE;
+";
+
+pub const VOTES: &str = "
+DEFINE TABLE IF NOT EXISTS votes SCHEMALESS;
+DEFINE FIELD IF NOT EXISTS height ON votes TYPE int;
+DEFINE FIELD IF NOT EXISTS time ON votes TYPE int;
+DEFINE FIELD IF NOT EXISTS proposal_id ON votes TYPE int;
+DEFINE FIELD IF NOT EXISTS option ON votes TYPE int;
+DEFINE FIELD IF NOT EXISTS staker ON votes TYPE option<string>;
+DEFINE FIELD IF NOT EXISTS kind ON votes TYPE option<string> ASSERT $value IN [NONE, 'hot', 'cold'];
+DEFINE FIELD IF NOT EXISTS weight ON votes TYPE option<int>;
+DEFINE INDEX IF NOT EXISTS votes_height ON votes FIELDS height UNIQUE;
+DEFINE INDEX IF NOT EXISTS votes_time ON votes FIELDS time;
+DEFINE INDEX IF NOT EXISTS votes_proposal ON votes FIELDS proposal_id, option;
+DEFINE INDEX IF NOT EXISTS votes_staker ON votes FIELDS staker, proposal_id;
+";
+
+pub const TALLIES: &str = "
+DEFINE FIELD IF NOT EXISTS height ON proposals TYPE option<int>;
+DEFINE TABLE IF NOT EXISTS tallies SCHEMALESS;
+DEFINE FIELD IF NOT EXISTS proposal_id ON tallies TYPE int;
+DEFINE FIELD IF NOT EXISTS height ON tallies TYPE int;
+DEFINE FIELD IF NOT EXISTS stats ON tallies TYPE object;
+DEFINE INDEX IF NOT EXISTS tallies_proposal ON tallies FIELDS proposal_id, height UNIQUE;
+DEFINE INDEX IF NOT EXISTS tallies_height ON tallies FIELDS height;
+";
+
+// Proposals from the registry are recorded before their first tally.
+pub const PROPOSAL_STATES: &str = "
+REMOVE FIELD IF EXISTS stats ON proposals;
+DEFINE FIELD IF NOT EXISTS stats ON proposals TYPE option<object>;
+DEFINE FIELD IF NOT EXISTS metadata ON proposals TYPE option<object>;
+DEFINE FIELD IF NOT EXISTS state ON proposals TYPE option<string>
+    ASSERT $value IN [NONE, 'pending', 'active', 'ended', 'passed', 'failed'];
+DEFINE FIELD IF NOT EXISTS state_height ON proposals TYPE option<int>;
+DEFINE INDEX IF NOT EXISTS proposals_state ON proposals FIELDS state;
+";
+
+pub const OPTION_STATS: &str = "
+REMOVE FIELD IF EXISTS stats ON proposals;
+REMOVE FIELD IF EXISTS stats ON tallies;
+DEFINE FIELD IF NOT EXISTS height_start ON tallies TYPE option<int>;
+DEFINE FIELD IF NOT EXISTS blocks_counted ON tallies TYPE option<int>;
+DEFINE FIELD IF NOT EXISTS options ON proposals TYPE option<array<object>>;
+DEFINE FIELD IF NOT EXISTS options ON tallies TYPE option<array<object>>;
+";
+
+pub const VOTERS: &str = "
+DEFINE TABLE IF NOT EXISTS voters SCHEMALESS;
+DEFINE FIELD IF NOT EXISTS proposal_id ON voters TYPE int;
+DEFINE FIELD IF NOT EXISTS staker ON voters TYPE string;
+DEFINE FIELD IF NOT EXISTS option ON voters TYPE int;
+DEFINE FIELD IF NOT EXISTS blocks ON voters TYPE int;
+DEFINE FIELD IF NOT EXISTS weight ON voters TYPE int;
+DEFINE FIELD IF NOT EXISTS changes ON voters TYPE int;
+DEFINE INDEX IF NOT EXISTS voters_proposal ON voters FIELDS proposal_id, option;
+DEFINE INDEX IF NOT EXISTS voters_staker ON voters FIELDS staker;
+
+DEFINE TABLE IF NOT EXISTS votechanges SCHEMALESS;
+DEFINE FIELD IF NOT EXISTS proposal_id ON votechanges TYPE int;
+DEFINE FIELD IF NOT EXISTS staker ON votechanges TYPE string;
+DEFINE FIELD IF NOT EXISTS height ON votechanges TYPE int;
+DEFINE FIELD IF NOT EXISTS previous_option ON votechanges TYPE int;
+DEFINE FIELD IF NOT EXISTS option ON votechanges TYPE int;
+DEFINE INDEX IF NOT EXISTS votechanges_voter ON votechanges FIELDS proposal_id, staker;
+DEFINE INDEX IF NOT EXISTS votechanges_height ON votechanges FIELDS height;
+";
+
+pub const TURNOUT: &str = "
+DEFINE TABLE IF NOT EXISTS turnout SCHEMALESS;
+DEFINE FIELD IF NOT EXISTS height ON turnout TYPE int;
+DEFINE FIELD IF NOT EXISTS time ON turnout TYPE int;
+DEFINE FIELD IF NOT EXISTS day ON turnout TYPE string;
+DEFINE FIELD IF NOT EXISTS staked ON turnout TYPE bool;
+DEFINE FIELD IF NOT EXISTS voted ON turnout TYPE bool;
+DEFINE FIELD IF NOT EXISTS proposal_id ON turnout TYPE option<int>;
+DEFINE INDEX IF NOT EXISTS turnout_height ON turnout FIELDS height UNIQUE;
+DEFINE INDEX IF NOT EXISTS turnout_day ON turnout FIELDS day;
+
+DEFINE TABLE IF NOT EXISTS turnoutdaily SCHEMALESS;
+DEFINE FIELD IF NOT EXISTS day ON turnoutdaily TYPE string;
+DEFINE FIELD IF NOT EXISTS blocks ON turnoutdaily TYPE int;
+
+DEFINE FIELD IF NOT EXISTS participation ON proposals TYPE option<object>;
+DEFINE FIELD IF NOT EXISTS participation ON tallies TYPE option<object>;
+";
+
+pub const RING_KEY_IMAGES: &str = "
+DEFINE FIELD OVERWRITE key_images ON rings TYPE option<array<string>>;
+DEFINE INDEX OVERWRITE rings_key_images ON rings FIELDS key_images;
+";
+
+// Malformed votes are recorded as unknown shapes too, and their total is kept in a counter.
+pub const UNKNOWN_SHAPES: &str = "
+DEFINE FIELD OVERWRITE kind ON unknownshapes TYPE string ASSERT $value IN ['vin', 'vout', 'vote'];
+DEFINE TABLE OVERWRITE counters SCHEMALESS;
+DEFINE FIELD OVERWRITE total ON counters TYPE int;
+UPSERT counters:unknownshapes SET total = count(SELECT id FROM unknownshapes);
+";
+
+// Amounts of blocks are integer satoshis, once those stored as coins are converted.
+pub const BLOCK_AMOUNTS: &str = "
+DEFINE FIELD OVERWRITE stakekernelvalue ON blocks TYPE option<int>;
+DEFINE FIELD OVERWRITE tx.*.vout.*.value ON blocks TYPE option<int>;
+DEFINE FIELD OVERWRITE tx.*.vout.*.smsgfeerate ON blocks TYPE option<int>;
+DEFINE FIELD OVERWRITE tx.*.vout.*.treasury_fund_cfwd ON blocks TYPE option<int>;
+DEFINE FIELD OVERWRITE tx.*.vout.*.ct_fee ON blocks TYPE option<int>;
+";
@@ -0,0 +1,393 @@
+// Classification of output scripts into known templates.
+use serde::{Deserialize, Serialize};
+
+const OP_0: u8 = 0x00;
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+const OP_1NEGATE: u8 = 0x4f;
+const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+const OP_IF: u8 = 0x63;
+const OP_ELSE: u8 = 0x67;
+const OP_ENDIF: u8 = 0x68;
+const OP_RETURN: u8 = 0x6a;
+const OP_DROP: u8 = 0x75;
+const OP_DUP: u8 = 0x76;
+const OP_EQUAL: u8 = 0x87;
+const OP_EQUALVERIFY: u8 = 0x88;
+const OP_SHA256: u8 = 0xa8;
+const OP_HASH160: u8 = 0xa9;
+const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+const OP_CHECKLOCKTIMEVERIFY: u8 = 0xb1;
+const OP_CHECKSEQUENCEVERIFY: u8 = 0xb2;
+// Particl repurposes OP_NOP9 to check whether the spending transaction is a coinstake.
+const OP_ISCOINSTAKE: u8 = 0xb8;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Op {
+    Push(Vec<u8>),
+    Code(u8),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScriptTemplate {
+    // Hashes are 20 bytes for HASH160 and 32 bytes for SHA256 based scripts.
+    Pubkey {
+        pubkey: String,
+    },
+    Pubkeyhash {
+        keyhash: String,
+    },
+    Scripthash {
+        scripthash: String,
+    },
+    Witness {
+        version: u8,
+        program: String,
+    },
+    Coldstake {
+        stake_keyhash: String,
+        spend: Box<ScriptTemplate>,
+    },
+    Multisig {
+        required: u8,
+        pubkeys: Vec<String>,
+    },
+    Timelocked {
+        // Block height or timestamp for absolute locks, sequence for relative ones.
+        lock: i64,
+        relative: bool,
+        script: Box<ScriptTemplate>,
+    },
+    Nulldata {
+        data: Vec<String>,
+    },
+    Nonstandard,
+}
+
+fn parse(script: &[u8]) -> Option<Vec<Op>> {
+    let mut ops = Vec::new();
+    let mut i = 0;
+    while i < script.len() {
+        let opcode = script[i];
+        i += 1;
+        let len = match opcode {
+            0x01..=0x4b => opcode as usize,
+            OP_PUSHDATA1 => {
+                i += 1;
+                *script.get(i - 1)? as usize
+            }
+            OP_PUSHDATA2 => {
+                i += 2;
+                u16::from_le_bytes(script.get(i - 2..i)?.try_into().ok()?) as usize
+            }
+            OP_PUSHDATA4 => {
+                i += 4;
+                u32::from_le_bytes(script.get(i - 4..i)?.try_into().ok()?) as usize
+            }
+            _ => {
+                ops.push(Op::Code(opcode));
+                continue;
+            }
+        };
+        ops.push(Op::Push(script.get(i..i + len)?.to_vec()));
+        i += len;
+    }
+    Some(ops)
+}
+
+fn is_hash(data: &[u8]) -> bool {
+    data.len() == 20 || data.len() == 32
+}
+
+fn is_pubkey(data: &[u8]) -> bool {
+    data.len() == 33 || data.len() == 65
+}
+
+fn hash_op(data: &[u8]) -> u8 {
+    match data.len() {
+        32 => OP_SHA256,
+        _ => OP_HASH160,
+    }
+}
+
+fn witness_version(op: &Op) -> Option<u8> {
+    match op {
+        Op::Code(OP_0) => Some(0),
+        op => small_int(op),
+    }
+}
+
+fn small_int(op: &Op) -> Option<u8> {
+    match op {
+        Op::Code(code) if (OP_1..=OP_16).contains(code) => Some(code - OP_1 + 1),
+        _ => None,
+    }
+}
+
+// Decodes a minimally encoded script number.
+fn script_num(op: &Op) -> Option<i64> {
+    match op {
+        Op::Code(OP_0) => Some(0),
+        Op::Code(OP_1NEGATE) => Some(-1),
+        Op::Push(data) if !data.is_empty() && data.len() <= 5 => {
+            let mut value: i64 = 0;
+            for (i, byte) in data.iter().enumerate() {
+                value |= (*byte as i64) << (8 * i);
+            }
+            let sign = 0x80_i64 << (8 * (data.len() - 1));
+            match value & sign {
+                0 => Some(value),
+                _ => Some(-(value & !sign)),
+            }
+        }
+        op => small_int(op).map(i64::from),
+    }
+}
+
+fn classify_ops(ops: &[Op]) -> ScriptTemplate {
+    use Op::*;
+    match ops {
+        [Code(OP_DUP), Code(hash), Push(keyhash), Code(OP_EQUALVERIFY), Code(OP_CHECKSIG)]
+            if is_hash(keyhash) && *hash == hash_op(keyhash) =>
+        {
+            ScriptTemplate::Pubkeyhash {
+                keyhash: hex::encode(keyhash),
+            }
+        }
+        [Code(hash), Push(scripthash), Code(OP_EQUAL)]
+            if is_hash(scripthash) && *hash == hash_op(scripthash) =>
+        {
+            ScriptTemplate::Scripthash {
+                scripthash: hex::encode(scripthash),
+            }
+        }
+        [Push(pubkey), Code(OP_CHECKSIG)] if is_pubkey(pubkey) => ScriptTemplate::Pubkey {
+            pubkey: hex::encode(pubkey),
+        },
+        [version @ Code(_), Push(program)]
+            if witness_version(version).is_some() && (2..=40).contains(&program.len()) =>
+        {
+            ScriptTemplate::Witness {
+                version: witness_version(version).unwrap(),
+                program: hex::encode(program),
+            }
+        }
+        [Code(OP_ISCOINSTAKE), Code(OP_IF), branches @ .., Code(OP_ENDIF)] => {
+            let split = branches.iter().position(|op| op == &Code(OP_ELSE));
+            let (stake, spend) = match split {
+                Some(split) => (&branches[..split], &branches[split + 1..]),
+                None => return ScriptTemplate::Nonstandard,
+            };
+            match (classify_ops(stake), classify_ops(spend)) {
+                (
+                    ScriptTemplate::Pubkeyhash { keyhash },
+                    spend @ ScriptTemplate::Pubkeyhash { .. },
+                )
+                | (
+                    ScriptTemplate::Pubkeyhash { keyhash },
+                    spend @ ScriptTemplate::Scripthash { .. },
+                ) => ScriptTemplate::Coldstake {
+                    stake_keyhash: keyhash,
+                    spend: Box::new(spend),
+                },
+                _ => ScriptTemplate::Nonstandard,
+            }
+        }
+        [required, keys @ .., total, Code(OP_CHECKMULTISIG)] => {
+            let pubkeys: Vec<String> = keys
+                .iter()
+                .filter_map(|op| match op {
+                    Push(key) if is_pubkey(key) => Some(hex::encode(key)),
+                    _ => None,
+                })
+                .collect();
+            match (small_int(required), small_int(total)) {
+                (Some(required), Some(total))
+                    if pubkeys.len() == keys.len()
+                        && total as usize == keys.len()
+                        && required <= total =>
+                {
+                    ScriptTemplate::Multisig { required, pubkeys }
+                }
+                _ => ScriptTemplate::Nonstandard,
+            }
+        }
+        [lock, Code(check), Code(OP_DROP), script @ ..]
+            if *check == OP_CHECKLOCKTIMEVERIFY || *check == OP_CHECKSEQUENCEVERIFY =>
+        {
+            match script_num(lock) {
+                Some(lock) => ScriptTemplate::Timelocked {
+                    lock,
+                    relative: *check == OP_CHECKSEQUENCEVERIFY,
+                    script: Box::new(classify_ops(script)),
+                },
+                None => ScriptTemplate::Nonstandard,
+            }
+        }
+        [Code(OP_RETURN), data @ ..] => ScriptTemplate::Nulldata {
+            data: data
+                .iter()
+                .filter_map(|op| match op {
+                    Push(data) => Some(hex::encode(data)),
+                    _ => None,
+                })
+                .collect(),
+        },
+        _ => ScriptTemplate::Nonstandard,
+    }
+}
+
+pub fn classify(script_hex: &str) -> ScriptTemplate {
+    match hex::decode(script_hex).ok().as_deref().and_then(parse) {
+        Some(ops) => classify_ops(&ops),
+        None => ScriptTemplate::Nonstandard,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ScriptTemplate::*;
+
+    const KEYHASH: &str = "1111111111111111111111111111111111111111";
+    const KEYHASH256: &str = "2222222222222222222222222222222222222222222222222222222222222222";
+    const SPENDHASH: &str = "3333333333333333333333333333333333333333";
+    const PUBKEY: &str = "024444444444444444444444444444444444444444444444444444444444444444";
+    const PUBKEY2: &str = "035555555555555555555555555555555555555555555555555555555555555555";
+
+    fn p2pkh(keyhash: &str) -> String {
+        format!("76a914{}88ac", keyhash)
+    }
+
+    fn pubkeyhash(keyhash: &str) -> ScriptTemplate {
+        Pubkeyhash {
+            keyhash: keyhash.to_string(),
+        }
+    }
+
+    #[test]
+    fn classify_cases() {
+        let uncompressed = format!("04{}", "66".repeat(64));
+        let cases = [
+            (
+                format!("21{}ac", PUBKEY),
+                Pubkey {
+                    pubkey: PUBKEY.to_string(),
+                },
+            ),
+            (
+                format!("41{}ac", uncompressed),
+                Pubkey {
+                    pubkey: uncompressed.clone(),
+                },
+            ),
+            (p2pkh(KEYHASH), pubkeyhash(KEYHASH)),
+            (format!("76a820{}88ac", KEYHASH256), pubkeyhash(KEYHASH256)),
+            (
+                format!("a914{}87", KEYHASH),
+                Scripthash {
+                    scripthash: KEYHASH.to_string(),
+                },
+            ),
+            (
+                format!("a820{}87", KEYHASH256),
+                Scripthash {
+                    scripthash: KEYHASH256.to_string(),
+                },
+            ),
+            (
+                format!("0014{}", KEYHASH),
+                Witness {
+                    version: 0,
+                    program: KEYHASH.to_string(),
+                },
+            ),
+            (
+                format!("5120{}", KEYHASH256),
+                Witness {
+                    version: 1,
+                    program: KEYHASH256.to_string(),
+                },
+            ),
+            (
+                format!("b863{}67{}68", p2pkh(KEYHASH), p2pkh(SPENDHASH)),
+                Coldstake {
+                    stake_keyhash: KEYHASH.to_string(),
+                    spend: Box::new(pubkeyhash(SPENDHASH)),
+                },
+            ),
+            (
+                format!("b863{}67a820{}8768", p2pkh(KEYHASH), KEYHASH256),
+                Coldstake {
+                    stake_keyhash: KEYHASH.to_string(),
+                    spend: Box::new(Scripthash {
+                        scripthash: KEYHASH256.to_string(),
+                    }),
+                },
+            ),
+            (
+                format!("5121{}21{}52ae", PUBKEY, PUBKEY2),
+                Multisig {
+                    required: 1,
+                    pubkeys: vec![PUBKEY.to_string(), PUBKEY2.to_string()],
+                },
+            ),
+            (
+                format!("03a08601b175{}", p2pkh(KEYHASH)),
+                Timelocked {
+                    lock: 100_000,
+                    relative: false,
+                    script: Box::new(pubkeyhash(KEYHASH)),
+                },
+            ),
+            (
+                format!("52b275{}", p2pkh(KEYHASH)),
+                Timelocked {
+                    lock: 2,
+                    relative: true,
+                    script: Box::new(pubkeyhash(KEYHASH)),
+                },
+            ),
+            (
+                "6a04deadbeef".to_string(),
+                Nulldata {
+                    data: vec!["deadbeef".to_string()],
+                },
+            ),
+            ("6a".to_string(), Nulldata { data: vec![] }),
+        ];
+        for (script, expected) in cases {
+            assert_eq!(classify(&script), expected, "{}", script);
+        }
+    }
+
+    #[test]
+    fn classify_rejects_malformed_scripts() {
+        let cases = [
+            // Hash length doesn't match the hash opcode.
+            format!("76a920{}88ac", KEYHASH256),
+            // Cold staking script without a spend branch.
+            format!("b863{}68", p2pkh(KEYHASH)),
+            // More signatures required than keys given.
+            format!("5221{}21{}51ae", PUBKEY, PUBKEY2),
+            // Lock which isn't a number.
+            format!("76b175{}", p2pkh(KEYHASH)),
+            // Truncated pushes.
+            format!("76a914{}", &KEYHASH[..20]),
+            "4c".to_string(),
+            "4c05aa".to_string(),
+            "4d01".to_string(),
+            "4e010000".to_string(),
+            "4effffffff".to_string(),
+            "not hex".to_string(),
+            String::new(),
+        ];
+        for script in cases {
+            assert_eq!(classify(&script), Nonstandard, "{}", script);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+// SecureMessaging paid message parameters carried by coinstake data outputs.
+use crate::console::{BlockData, Vout};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmsgParams {
+    pub height: u64,
+    pub time: u64,
+    // Satoshis.
+    pub feerate: Option<u64>,
+    // Compact target as reported by particld, for example '1f0fffff'.
+    pub difficulty: Option<String>,
+    pub target: Option<f64>,
+    // Changes against the previous block. None if it carried no parameters.
+    pub feerate_change: Option<i64>,
+    // Ratio of the previous target to the current one, above 1 means harder.
+    pub difficulty_change: Option<f64>,
+}
+
+// Expands compact target bits into the target value.
+fn target(compact: &str) -> Option<f64> {
+    let bits = u32::from_str_radix(compact, 16).ok()?;
+    let exponent = (bits >> 24) as i32;
+    let mantissa = bits & 0x007f_ffff;
+    // Small exponents shift the mantissa right, dropping the bytes which don't fit, as particld does.
+    if exponent <= 3 {
+        return Some((mantissa >> (8 * (3 - exponent))) as f64);
+    }
+    Some(mantissa as f64 * 256f64.powi(exponent - 3))
+}
+
+pub fn params(blockdata: &BlockData, previous: Option<&SmsgParams>) -> Option<SmsgParams> {
+    let (smsgfeerate, smsgdifficulty) = match blockdata.tx.first()?.vout.first()? {
+        Vout::Data {
+            smsgfeerate,
+            smsgdifficulty,
+            ..
+        } if smsgfeerate.is_some() || smsgdifficulty.is_some() => (smsgfeerate, smsgdifficulty),
+        _ => return None,
+    };
+    let feerate = smsgfeerate.map(|feerate| feerate.sat());
+    let target = smsgdifficulty.as_deref().and_then(target);
+    let feerate_change = match (feerate, previous.and_then(|previous| previous.feerate)) {
+        (Some(current), Some(previous)) => Some(current as i64 - previous as i64),
+        _ => None,
+    };
+    let difficulty_change = match (target, previous.and_then(|previous| previous.target)) {
+        (Some(current), Some(previous)) if current > 0.0 => Some(previous / current),
+        _ => None,
+    };
+    Some(SmsgParams {
+        height: blockdata.height,
+        time: blockdata.time,
+        feerate,
+        difficulty: smsgdifficulty.clone(),
+        target,
+        feerate_change,
+        difficulty_change,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_expands_compact_bits() {
+        let cases = [
+            ("1f0fffff", Some(0x0f_ffff as f64 * 256f64.powi(28))),
+            ("1d00ffff", Some(0xffff as f64 * 256f64.powi(26))),
+            ("04123456", Some(0x1234_5600 as f64)),
+            ("03123456", Some(0x12_3456 as f64)),
+            ("02123456", Some(0x1234 as f64)),
+            ("01123456", Some(0x12 as f64)),
+            ("00123456", Some(0.0)),
+            // The sign bit isn't part of the mantissa.
+            ("04923456", Some(0x1234_5600 as f64)),
+            ("not hex", None),
+            ("", None),
+        ];
+        for (compact, expected) in cases {
+            assert_eq!(target(compact), expected, "{}", compact);
+        }
+    }
+}
@@ -0,0 +1,44 @@
+// Blocks staked by hot stakers and cold-staking stake keys.
+use crate::{console::BlockData, pools::Pool, rewards::CoinstakeReward};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StakerKind {
+    // Staked with the coins' own key, identified by the staking address.
+    Hot,
+    // Staked on behalf of the owner, identified by the stake-only address of the pool.
+    Cold,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stake {
+    pub height: u64,
+    pub time: u64,
+    pub staker: String,
+    pub kind: StakerKind,
+    // Satoshis.
+    pub reward: u64,
+}
+
+pub fn staker(
+    coinstake: &CoinstakeReward,
+    coldstaking: Option<&Pool>,
+) -> Option<(String, StakerKind)> {
+    match coldstaking {
+        Some(pool) => Some((pool.pubkey.clone(), StakerKind::Cold)),
+        None => Some((coinstake.staker_address.clone()?, StakerKind::Hot)),
+    }
+}
+
+pub fn stake(blockdata: &BlockData) -> Option<Stake> {
+    let coinstake = blockdata.coinstake.as_ref()?;
+    let (staker, kind) = staker(coinstake, blockdata.coldstaking.as_ref())?;
+    Some(Stake {
+        height: blockdata.height,
+        time: blockdata.time,
+        staker,
+        kind,
+        reward: coinstake.reward,
+    })
+}
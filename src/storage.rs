@@ -0,0 +1,139 @@
+// Pruning of block fields which aren't needed by the chosen storage profile.
+use crate::console::BlockData;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum StorageProfile {
+    // Everything particld reports.
+    Full,
+    // Drops raw transactions, range proofs, scripts and witnesses.
+    Analytics,
+    // Keeps block headers with staking and governance data only.
+    Minimal,
+}
+
+// Keys dropped from every transaction, input and output in the analytics profile.
+const ANALYTICS_PRUNED: [&str; 5] = ["hex", "asm", "rangeproof", "scriptSig", "txinwitness"];
+
+// Everything derived from the block is computed before storing it, so pruned fields are only lost from the stored block itself.
+pub fn prune(blockdata: &BlockData, profile: StorageProfile) -> Result<Value, Box<dyn Error>> {
+    let mut block = serde_json::to_value(blockdata)?;
+    match profile {
+        StorageProfile::Full => {}
+        StorageProfile::Analytics => {
+            if let Some(txs) = block.get_mut("tx").and_then(Value::as_array_mut) {
+                txs.iter_mut().for_each(strip);
+            }
+        }
+        StorageProfile::Minimal => {
+            if let Some(block) = block.as_object_mut() {
+                block.remove("tx");
+            }
+        }
+    }
+    Ok(block)
+}
+
+fn strip(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            ANALYTICS_PRUNED.iter().for_each(|key| {
+                map.remove(*key);
+            });
+            // Data outputs carry votes and smsg parameters in data_hex, blind ones only ciphertext.
+            if !is_data(map) {
+                map.remove("data_hex");
+            }
+            map.values_mut().for_each(strip);
+        }
+        Value::Array(values) => values.iter_mut().for_each(strip),
+        _ => {}
+    }
+}
+
+fn is_data(map: &Map<String, Value>) -> bool {
+    map.get("type").and_then(Value::as_str) == Some("data")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::{
+        tests::{blockdata, standard_vin, standard_vout, tx},
+        Vote,
+    };
+    use serde_json::json;
+
+    fn block() -> BlockData {
+        let anon_vin = json!({
+            "type": "anon",
+            "num_inputs": 1,
+            "ring_size": 3,
+            "txinwitness": ["aa"],
+            "sequence": 4294967295u64,
+        });
+        let mut spend = tx(
+            "spend",
+            json!([standard_vin("public", 0), anon_vin]),
+            json!([
+                {"n": 0, "type": "data", "data_hex": "0a0b", "ct_fee": 0.0001},
+                standard_vout(1, "a", 1),
+                {"n": 2, "type": "blind", "valueCommitment": "08", "data_hex": "0c", "rangeproof": "0d"},
+            ]),
+        );
+        spend["hex"] = json!("0e");
+        spend["vin"][0]["scriptSig"] = json!({"asm": "0f", "hex": "0f"});
+        spend["vout"][1]["scriptPubKey"]["hex"] = json!("76a9");
+        blockdata(5, vec![spend])
+    }
+
+    #[test]
+    fn full_keeps_everything() {
+        let blockdata = block();
+        assert_eq!(
+            prune(&blockdata, StorageProfile::Full).unwrap(),
+            serde_json::to_value(&blockdata).unwrap()
+        );
+    }
+
+    #[test]
+    fn analytics_drops_raw_data_only() {
+        let pruned = prune(&block(), StorageProfile::Analytics).unwrap();
+        let tx = &pruned["tx"][0];
+        assert_eq!(tx.get("hex"), None);
+        assert_eq!(tx["vin"][0].get("scriptSig"), None);
+        assert_eq!(tx["vin"][0]["txid"], "public");
+        assert_eq!(tx["vin"][1].get("txinwitness"), None);
+        assert_eq!(tx["vin"][1]["ring_size"], 3);
+        // Data outputs keep their payload, which carries votes and smsg parameters.
+        assert_eq!(tx["vout"][0]["data_hex"], "0a0b");
+        assert_eq!(tx["vout"][0]["ct_fee"], 10000);
+        let script = &tx["vout"][1]["scriptPubKey"];
+        assert_eq!((script.get("asm"), script.get("hex")), (None, None));
+        assert_eq!(script["addresses"], json!(["a"]));
+        assert_eq!(tx["vout"][1]["valueSat"], 1);
+        let blind = &tx["vout"][2];
+        assert_eq!(
+            (blind.get("data_hex"), blind.get("rangeproof")),
+            (None, None)
+        );
+        assert_eq!(blind["valueCommitment"], "08");
+        assert_eq!(pruned["height"], 5);
+    }
+
+    #[test]
+    fn minimal_drops_transactions() {
+        let mut blockdata = block();
+        blockdata.voting_info = Some(Vote {
+            proposal_id: 5,
+            voted_for_option: 1,
+        });
+        let pruned = prune(&blockdata, StorageProfile::Minimal).unwrap();
+        assert_eq!(pruned.get("tx"), None);
+        assert_eq!(pruned["height"], 5);
+        assert_eq!(pruned["voting_info"]["proposal_id"], 5);
+        assert_eq!(pruned["hash"], blockdata.hash);
+    }
+}
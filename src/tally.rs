@@ -0,0 +1,202 @@
+// Proposal tallies computed from the recorded votes.
+use crate::console::{basis_points, OptionStats, Proposal};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Block before the first recorded vote, where tallies of particld start as well.
+pub const TALLY_START: u64 = 616958;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptionVotes {
+    pub option: u64,
+    pub votes: u64,
+    // Satoshis.
+    pub weight: u64,
+}
+
+// Mirrors tallyvotes of particld: every block of the range counts, voted or not,
+// and percentages are rounded the same way before being turned into basis points.
+pub fn tally(proposal_id: u64, from_height: u64, height: u64, votes: &[OptionVotes]) -> Proposal {
+    let blocks = (height + 1).saturating_sub(from_height).max(1);
+    let weight: u64 = votes.iter().map(|option| option.weight).sum();
+    let mut options: Vec<OptionStats> = votes
+        .iter()
+        .map(|option| {
+            let percentage = format!("{:.2}", option.votes as f32 / blocks as f32 * 100.0);
+            OptionStats {
+                option: option.option,
+                label: None,
+                blocks: option.votes,
+                // Always parses, being formatted just above.
+                share: basis_points(&percentage).unwrap_or_default(),
+                weight: Some(option.weight),
+                weight_share: (weight > 0)
+                    .then(|| (option.weight as u128 * 10000 / weight as u128) as u64),
+            }
+        })
+        .collect();
+    options.sort_by_key(|option| option.option);
+    Proposal {
+        proposal_id,
+        height_start: from_height,
+        height,
+        blocks_counted: blocks,
+        blocks_voted: votes.iter().map(|option| option.votes).sum(),
+        weight: Some(weight),
+        options,
+        participation: None,
+    }
+}
+
+// Stats stored before they were typed, as 'Option N' keys with (blocks, basis points).
+pub fn from_legacy(proposal_id: u64, height: u64, stats: &HashMap<String, (u64, u64)>) -> Proposal {
+    let mut options: Vec<OptionStats> = stats
+        .iter()
+        .filter_map(|(key, (blocks, share))| {
+            Some(OptionStats {
+                option: key.strip_prefix("Option ")?.parse::<u64>().ok()?,
+                label: None,
+                blocks: *blocks,
+                share: *share,
+                weight: None,
+                weight_share: None,
+            })
+        })
+        .collect();
+    options.sort_by_key(|option| option.option);
+    Proposal {
+        proposal_id,
+        height_start: TALLY_START,
+        height,
+        blocks_counted: (height + 1).saturating_sub(TALLY_START),
+        blocks_voted: options.iter().map(|option| option.blocks).sum(),
+        weight: None,
+        options,
+        participation: None,
+    }
+}
+
+// Differences in blocks counted and in votes or share of any option, as 'what: native vs node'.
+pub fn mismatches(native: &Proposal, node: &Proposal) -> Vec<String> {
+    let mut mismatches = Vec::new();
+    if native.blocks_counted != node.blocks_counted {
+        mismatches.push(format!(
+            "blocks counted: {} vs {}",
+            native.blocks_counted, node.blocks_counted
+        ));
+    }
+    let votes = |proposal: &Proposal, option: u64| {
+        proposal
+            .options
+            .iter()
+            .find(|stats| stats.option == option)
+            .map(|stats| (stats.blocks, stats.share))
+    };
+    let mut options: Vec<u64> = native
+        .options
+        .iter()
+        .chain(node.options.iter())
+        .map(|stats| stats.option)
+        .collect();
+    options.sort();
+    options.dedup();
+    for option in options {
+        if votes(native, option) != votes(node, option) {
+            mismatches.push(format!(
+                "option {}: {:?} vs {:?}",
+                option,
+                votes(native, option),
+                votes(node, option)
+            ));
+        }
+    }
+    mismatches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn votes(option: u64, votes: u64, weight: u64) -> OptionVotes {
+        OptionVotes {
+            option,
+            votes,
+            weight,
+        }
+    }
+
+    fn stats(proposal: &Proposal) -> Vec<(u64, u64, u64, Option<u64>)> {
+        proposal
+            .options
+            .iter()
+            .map(|stats| (stats.option, stats.blocks, stats.share, stats.weight_share))
+            .collect()
+    }
+
+    #[test]
+    fn tally_counts_every_block_of_the_range() {
+        let proposal = tally(7, 100, 102, &[votes(2, 2, 3), votes(1, 1, 1)]);
+        assert_eq!(proposal.proposal_id, 7);
+        assert_eq!((proposal.height_start, proposal.height), (100, 102));
+        assert_eq!(proposal.blocks_counted, 3);
+        assert_eq!(proposal.blocks_voted, 3);
+        assert_eq!(proposal.weight, Some(4));
+        assert_eq!(
+            stats(&proposal),
+            [(1, 1, 3333, Some(2500)), (2, 2, 6667, Some(7500))]
+        );
+    }
+
+    #[test]
+    fn tally_shares_follow_tallyvotes_rounding() {
+        // (votes, blocks counted, expected basis points)
+        let cases = [
+            (7, 720, 97),
+            (1, 3, 3333),
+            (1, 8, 1250),
+            (0, 10, 0),
+            (10, 10, 10000),
+        ];
+        for (voted, blocks, expected) in cases {
+            let proposal = tally(1, 1, blocks, &[votes(1, voted, 0)]);
+            assert_eq!(proposal.options[0].share, expected, "{}/{}", voted, blocks);
+            assert_eq!(proposal.options[0].weight_share, None);
+        }
+        // An empty range still counts the block it ends at.
+        assert_eq!(tally(1, 10, 5, &[]).blocks_counted, 1);
+    }
+
+    #[test]
+    fn from_legacy_types_option_keys() {
+        let legacy = HashMap::from([
+            ("Option 2".to_string(), (4, 4000)),
+            ("Option 1".to_string(), (1, 1000)),
+            ("proposal".to_string(), (0, 0)),
+        ]);
+        let proposal = from_legacy(3, TALLY_START + 9, &legacy);
+        assert_eq!(proposal.height_start, TALLY_START);
+        assert_eq!(proposal.blocks_counted, 10);
+        assert_eq!(proposal.blocks_voted, 5);
+        assert_eq!(proposal.weight, None);
+        assert_eq!(stats(&proposal), [(1, 1, 1000, None), (2, 4, 4000, None)]);
+    }
+
+    #[test]
+    fn mismatches_list_every_difference() {
+        let native = tally(1, 100, 109, &[votes(1, 2, 5), votes(2, 3, 5)]);
+        assert!(mismatches(&native, &native.clone()).is_empty());
+        let node = tally(1, 100, 110, &[votes(1, 2, 0), votes(3, 1, 0)]);
+        assert_eq!(
+            mismatches(&native, &node),
+            [
+                "blocks counted: 10 vs 11",
+                "option 1: Some((2, 2000)) vs Some((2, 1818))",
+                "option 2: Some((3, 3000)) vs None",
+                "option 3: None vs Some((1, 909))",
+            ]
+        );
+        // Weights are unknown to tallyvotes and not compared.
+        let unweighted = tally(1, 100, 109, &[votes(1, 2, 0), votes(2, 3, 0)]);
+        assert!(mismatches(&native, &unweighted).is_empty());
+    }
+}
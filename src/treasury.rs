@@ -0,0 +1,146 @@
+use crate::console::{BlockData, Vout};
+use serde::{Deserialize, Serialize};
+
+// Treasury fund addresses from the Particl Core mainnet chain parameters.
+pub const TREASURY_ADDRESSES: [&str; 3] = [
+    "RJAPhgckEgRGVPZa9WoGSWW24spskSfLTQ",
+    "RBiiQBnQsVPPQkUaJVQTjsZM9K2xMKozST",
+    "RQYUDd3EJohpjq62So4ftcV5XZfxZxJPe9",
+];
+
+pub fn is_treasury(address: &str) -> bool {
+    TREASURY_ADDRESSES.contains(&address)
+}
+
+// Treasury ledger entry of a single block. Amounts are in satoshis.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreasuryEntry {
+    pub height: u64,
+    pub time: u64,
+    // Amount accumulated for the next payout.
+    pub cfwd: u64,
+    pub payout: u64,
+    // Treasury share of this block's reward.
+    pub contribution: i64,
+    // Public balance of the treasury addresses after this block.
+    pub balance: u64,
+}
+
+pub fn entry(
+    blockdata: &BlockData,
+    previous: Option<&TreasuryEntry>,
+    balance: u64,
+) -> TreasuryEntry {
+    let cfwd = match blockdata.tx.first().and_then(|tx| tx.vout.first()) {
+        Some(Vout::Data {
+            treasury_fund_cfwd: Some(cfwd),
+            ..
+        }) => cfwd.sat(),
+        _ => 0,
+    };
+    let payout = blockdata
+        .coinstake
+        .as_ref()
+        .map_or(0, |coinstake| coinstake.treasury);
+    let previous_cfwd = previous.map_or(0, |previous| previous.cfwd);
+    TreasuryEntry {
+        height: blockdata.height,
+        time: blockdata.time,
+        cfwd,
+        payout,
+        contribution: payout as i64 + cfwd as i64 - previous_cfwd as i64,
+        balance,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        console::tests::{blockdata, standard_vout, tx},
+        rewards::CoinstakeReward,
+    };
+    use serde_json::json;
+
+    // Block carrying the given carry-forward and paying the given amount to the treasury.
+    fn block(cfwd: Option<f64>, payout: u64) -> BlockData {
+        let mut data = json!({"n": 0, "type": "data", "data_hex": ""});
+        if let Some(cfwd) = cfwd {
+            data["treasury_fund_cfwd"] = json!(cfwd);
+        }
+        let mut blockdata = blockdata(2, vec![tx("coinstake", json!([]), json!([data]))]);
+        blockdata.coinstake = Some(CoinstakeReward {
+            staked: 0,
+            outputs: 0,
+            reward: 0,
+            staker: 0,
+            treasury: payout,
+            pool_fee: 0,
+            staker_address: None,
+            pool_fee_address: None,
+        });
+        blockdata
+    }
+
+    fn previous(cfwd: u64) -> TreasuryEntry {
+        TreasuryEntry {
+            height: 1,
+            time: 0,
+            cfwd,
+            payout: 0,
+            contribution: 0,
+            balance: 0,
+        }
+    }
+
+    #[test]
+    fn entry_cases() {
+        // (carry-forward, payout, previous carry-forward, expected cfwd, payout and contribution)
+        let cases = [
+            // Contribution is only carried forward.
+            (Some(1.5), 0, Some(1_00000000), (1_50000000, 0, 50000000)),
+            // Payout block empties the carry-forward into the treasury output.
+            (
+                Some(0.0),
+                1_60000000,
+                Some(1_50000000),
+                (0, 1_60000000, 10000000),
+            ),
+            (
+                None,
+                1_60000000,
+                Some(1_50000000),
+                (0, 1_60000000, 10000000),
+            ),
+            // No treasury output at all, as before the treasury existed.
+            (None, 0, None, (0, 0, 0)),
+        ];
+        for (cfwd, payout, previous_cfwd, expected) in cases {
+            let previous = previous_cfwd.map(previous);
+            let entry = entry(&block(cfwd, payout), previous.as_ref(), 42);
+            assert_eq!(
+                (entry.cfwd, entry.payout, entry.contribution),
+                expected,
+                "{:?} {} {:?}",
+                cfwd,
+                payout,
+                previous_cfwd
+            );
+            assert_eq!((entry.height, entry.balance), (2, 42));
+        }
+    }
+
+    #[test]
+    fn entry_without_coinstake_pays_nothing() {
+        let blockdata = blockdata(
+            2,
+            vec![tx(
+                "coinbase",
+                json!([]),
+                json!([standard_vout(0, "miner", 1)]),
+            )],
+        );
+        let entry = entry(&blockdata, Some(&previous(0)), 0);
+        assert_eq!((entry.cfwd, entry.payout, entry.contribution), (0, 0, 0));
+    }
+}
@@ -0,0 +1,135 @@
+// Governance turnout of staked blocks and participation in proposals.
+use crate::{console::BlockData, privacy::day, registry::ProposalMeta};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockTurnout {
+    pub height: u64,
+    pub time: u64,
+    pub day: String,
+    // Proof of stake block, so it could have carried a vote.
+    pub staked: bool,
+    pub voted: bool,
+    pub proposal_id: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Participation {
+    pub start_height: u64,
+    pub end_height: u64,
+    // Blocks within the voting window and those of them voting on the proposal.
+    pub blocks: u64,
+    pub voted: u64,
+    // Basis points.
+    pub rate: u64,
+}
+
+// The coinstake is only decomposed for blocks with resolvable inputs, while every
+// proof of stake block has a stake hash in every storage profile.
+pub fn block(blockdata: &BlockData) -> BlockTurnout {
+    recorded(
+        blockdata.height,
+        blockdata.time,
+        blockdata.hashproofofstake.is_some(),
+        blockdata.voting_info.as_ref().map(|vote| vote.proposal_id),
+    )
+}
+
+// Also used to replay blocks already in the database.
+pub fn recorded(height: u64, time: u64, staked: bool, proposal_id: Option<u64>) -> BlockTurnout {
+    BlockTurnout {
+        height,
+        time,
+        day: day(time),
+        staked,
+        voted: proposal_id.is_some(),
+        proposal_id,
+    }
+}
+
+// Voting window is the period from the registry, or else starts with the first vote.
+// Either way it ends at the given height at the latest.
+pub fn window(meta: Option<&ProposalMeta>, first_vote: u64, height: u64) -> (u64, u64) {
+    let start = meta
+        .and_then(|meta| meta.start_height)
+        .unwrap_or(first_vote);
+    let end = meta
+        .and_then(|meta| meta.end_height)
+        .map_or(height, |end| end.min(height));
+    (start, end)
+}
+
+pub fn participation(start_height: u64, end_height: u64, voted: u64) -> Participation {
+    let blocks = (end_height + 1).saturating_sub(start_height);
+    Participation {
+        start_height,
+        end_height,
+        blocks,
+        voted,
+        rate: match blocks {
+            0 => 0,
+            blocks => voted * 10000 / blocks,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::tests::blockdata;
+    use std::collections::BTreeMap;
+
+    fn meta(start_height: Option<u64>, end_height: Option<u64>) -> ProposalMeta {
+        ProposalMeta {
+            proposal_id: 5,
+            title: String::new(),
+            url: None,
+            options: BTreeMap::new(),
+            start_height,
+            end_height,
+            threshold: None,
+            passing_option: 1,
+        }
+    }
+
+    #[test]
+    fn recorded_blocks_vote_when_they_name_a_proposal() {
+        let voted = recorded(10, 86400, true, Some(5));
+        assert!(voted.voted);
+        assert_eq!(voted.day, day(86400));
+        assert!(!recorded(11, 86400, true, None).voted);
+    }
+
+    #[test]
+    fn blocks_are_staked_by_their_proof_of_stake() {
+        let mut staked = blockdata(10, Vec::new());
+        assert!(!block(&staked).staked);
+        // Staked, but its coinstake spends outputs which couldn't be resolved.
+        staked.hashproofofstake = Some(format!("{:064x}", 1));
+        assert!(staked.coinstake.is_none());
+        assert!(block(&staked).staked);
+    }
+
+    #[test]
+    fn window_prefers_the_registry_and_ends_at_the_height() {
+        // (registry period, expected window at height 100 with the first vote at 20)
+        let cases = [
+            (None, (20, 100)),
+            (Some(meta(None, None)), (20, 100)),
+            (Some(meta(Some(10), Some(50))), (10, 50)),
+            (Some(meta(Some(10), Some(500))), (10, 100)),
+        ];
+        for (meta, expected) in cases {
+            assert_eq!(window(meta.as_ref(), 20, 100), expected);
+        }
+    }
+
+    #[test]
+    fn participation_rates_in_basis_points() {
+        let rate = |start, end, voted| participation(start, end, voted).rate;
+        assert_eq!(participation(1, 3, 1).blocks, 3);
+        assert_eq!(rate(1, 3, 1), 3333);
+        assert_eq!(rate(1, 4, 4), 10000);
+        assert_eq!(rate(5, 4, 0), 0);
+    }
+}
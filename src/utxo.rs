@@ -0,0 +1,115 @@
+// Tracking of the unspent transaction outputs set.
+use crate::{
+    address::{self, ColdstakeAddresses},
+    console::{BlockData, Vin, Vout},
+    script::ScriptTemplate,
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Utxo {
+    pub outpoint: String,
+    pub txid: String,
+    pub n: u64,
+    pub height: u64,
+    #[serde(rename(deserialize = "type", serialize = "type"))]
+    pub output_type: String,
+    // Public outputs only. Blind outputs carry a commitment instead.
+    pub valuesat: Option<u64>,
+    pub value_commitment: Option<String>,
+    pub addresses: Option<Vec<String>>,
+    pub stakeaddresses: Option<Vec<String>>,
+    pub template: Option<ScriptTemplate>,
+    pub coldstake: Option<ColdstakeAddresses>,
+    pub spent: Option<Spent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spent {
+    pub txid: String,
+    pub height: u64,
+}
+
+// Reference from a transaction input to the output it consumes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Spend {
+    pub outpoint: String,
+    pub spent: Spent,
+}
+
+pub fn outpoint(txid: &str, n: u64) -> String {
+    format!("{}:{}", txid, n)
+}
+
+// Outputs created by the block. Data outputs are unspendable and anon outputs
+// are consumed through key images, so neither can ever be referenced by a Vin.
+pub fn created(blockdata: &BlockData) -> Vec<Utxo> {
+    let mut utxos = Vec::new();
+    for tx in blockdata.tx.iter() {
+        for vout in tx.vout.iter() {
+            let utxo = match vout {
+                Vout::Standard {
+                    n,
+                    vout_type,
+                    valuesat,
+                    scriptpubkey,
+                    ..
+                } => Utxo {
+                    outpoint: outpoint(&tx.txid, *n),
+                    txid: tx.txid.clone(),
+                    n: *n,
+                    height: blockdata.height,
+                    output_type: vout_type.clone(),
+                    valuesat: Some(*valuesat),
+                    value_commitment: None,
+                    addresses: scriptpubkey.addresses.clone(),
+                    stakeaddresses: scriptpubkey.stakeaddresses.clone(),
+                    template: scriptpubkey.template.clone(),
+                    coldstake: scriptpubkey.template.as_ref().and_then(address::coldstake),
+                    spent: None,
+                },
+                Vout::Blind {
+                    n,
+                    vout_type,
+                    value_commitment,
+                    ..
+                } => Utxo {
+                    outpoint: outpoint(&tx.txid, *n),
+                    txid: tx.txid.clone(),
+                    n: *n,
+                    height: blockdata.height,
+                    output_type: vout_type.clone(),
+                    valuesat: None,
+                    value_commitment: Some(value_commitment.clone()),
+                    addresses: None,
+                    stakeaddresses: None,
+                    template: None,
+                    coldstake: None,
+                    spent: None,
+                },
+                _ => continue,
+            };
+            utxos.push(utxo);
+        }
+    }
+    utxos
+}
+
+// Outputs consumed by the inputs of the block.
+pub fn spends(blockdata: &BlockData) -> Vec<Spend> {
+    let mut spends = Vec::new();
+    for tx in blockdata.tx.iter() {
+        for vin in tx.vin.iter() {
+            if let Vin::Standard { txid, vout, .. } = vin {
+                spends.push(Spend {
+                    outpoint: outpoint(txid, *vout),
+                    spent: Spent {
+                        txid: tx.txid.clone(),
+                        height: blockdata.height,
+                    },
+                });
+            }
+        }
+    }
+    spends
+}
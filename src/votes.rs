@@ -0,0 +1,149 @@
+// Votes cast by stakers in the coinstake of their blocks.
+use crate::{
+    amount::Amount,
+    console::{BlockData, Vote},
+    pools::Pool,
+    rewards::CoinstakeReward,
+    stakers::{self, StakerKind},
+};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VoteRecord {
+    pub height: u64,
+    pub time: u64,
+    pub proposal_id: u64,
+    pub option: u64,
+    // Staking address of hot stakers, stake-only address of the pool for cold ones.
+    pub staker: Option<String>,
+    pub kind: Option<StakerKind>,
+    pub pool_url: Option<String>,
+    // Satoshis staked by the coinstake, falling back to the kernel value.
+    pub weight: Option<u64>,
+}
+
+// Fields a vote is recorded from, which blocks keep in every storage profile.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VotedBlock {
+    pub height: u64,
+    pub time: u64,
+    pub voting_info: Option<Vote>,
+    pub coinstake: Option<CoinstakeReward>,
+    pub coldstaking: Option<Pool>,
+    pub stakekernelvalue: Option<Amount>,
+}
+
+pub fn vote(blockdata: &BlockData) -> Option<VoteRecord> {
+    record(&VotedBlock {
+        height: blockdata.height,
+        time: blockdata.time,
+        voting_info: blockdata.voting_info.clone(),
+        coinstake: blockdata.coinstake.clone(),
+        coldstaking: blockdata.coldstaking.clone(),
+        stakekernelvalue: blockdata.stakekernelvalue,
+    })
+}
+
+pub fn record(block: &VotedBlock) -> Option<VoteRecord> {
+    let vote = block.voting_info.as_ref()?;
+    let staker = block
+        .coinstake
+        .as_ref()
+        .and_then(|coinstake| stakers::staker(coinstake, block.coldstaking.as_ref()));
+    let weight = match &block.coinstake {
+        Some(coinstake) => Some(coinstake.staked),
+        None => block.stakekernelvalue.map(|value| value.sat()),
+    };
+    Some(VoteRecord {
+        height: block.height,
+        time: block.time,
+        proposal_id: vote.proposal_id,
+        option: vote.voted_for_option,
+        staker: staker.as_ref().map(|(staker, _)| staker.clone()),
+        kind: staker.as_ref().map(|(_, kind)| *kind),
+        pool_url: block.coldstaking.as_ref().and_then(|pool| pool.url.clone()),
+        weight,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pools::{identify, POOLS};
+
+    fn block(coinstake: Option<CoinstakeReward>, coldstaking: Option<Pool>) -> VotedBlock {
+        VotedBlock {
+            height: 10,
+            time: 20,
+            voting_info: Some(Vote {
+                proposal_id: 5,
+                voted_for_option: 2,
+            }),
+            coinstake,
+            coldstaking,
+            stakekernelvalue: Some(Amount(700)),
+        }
+    }
+
+    fn coinstake(staked: u64) -> CoinstakeReward {
+        CoinstakeReward {
+            staked,
+            outputs: 0,
+            reward: 0,
+            staker: 0,
+            treasury: 0,
+            pool_fee: 0,
+            staker_address: Some("hot".to_string()),
+            pool_fee_address: None,
+        }
+    }
+
+    #[test]
+    fn record_cases() {
+        let pool = identify(POOLS[0].pubkey);
+        // (coinstake, coldstaking, expected staker, kind, pool url and weight)
+        let cases = [
+            (
+                Some(coinstake(900)),
+                None,
+                (Some("hot"), Some(StakerKind::Hot), None, Some(900)),
+            ),
+            (
+                Some(coinstake(900)),
+                Some(pool.clone()),
+                (
+                    Some(POOLS[0].pubkey),
+                    Some(StakerKind::Cold),
+                    Some(POOLS[0].url),
+                    Some(900),
+                ),
+            ),
+            // Without a decomposed coinstake the staker is unknown and the kernel value weighs in.
+            (
+                None,
+                Some(pool.clone()),
+                (None, None, Some(POOLS[0].url), Some(700)),
+            ),
+            (None, None, (None, None, None, Some(700))),
+        ];
+        for (coinstake, coldstaking, expected) in cases {
+            let vote = record(&block(coinstake, coldstaking)).unwrap();
+            assert_eq!(
+                (vote.height, vote.time, vote.proposal_id, vote.option),
+                (10, 20, 5, 2)
+            );
+            assert_eq!(
+                (
+                    vote.staker.as_deref(),
+                    vote.kind,
+                    vote.pool_url.as_deref(),
+                    vote.weight
+                ),
+                expected
+            );
+        }
+        let mut unvoted = block(None, None);
+        unvoted.voting_info = None;
+        assert!(record(&unvoted).is_none());
+    }
+}